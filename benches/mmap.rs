@@ -0,0 +1,48 @@
+//! Compares searching a few large, explicitly given files with and without
+//! memory maps.
+//!
+//! Run with `cargo bench --bench mmap`.
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{self, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+const FILE_COUNT: usize = 4;
+const FUNCTIONS_PER_FILE: usize = 50_000;
+const RUNS: u32 = 10;
+
+fn main() {
+    let dir = env::temp_dir().join(format!("tree-sitter-grep-mmap-bench-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source = (0..FUNCTIONS_PER_FILE)
+        .map(|index| format!("fn function_{index}(x: usize) -> usize {{\n    x + {index}\n}}\n\n"))
+        .collect::<String>();
+    let paths = (0..FILE_COUNT)
+        .map(|index| {
+            let path = dir.join(format!("file_{index}.rs"));
+            fs::write(&path, &source).unwrap();
+            path
+        })
+        .collect::<Vec<PathBuf>>();
+
+    for flag in ["--mmap", "--no-mmap"] {
+        let mut total = Duration::ZERO;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            let status = Command::new(env!("CARGO_BIN_EXE_tree-sitter-grep"))
+                .args(["-q", "(function_item) @function_item", "--count", flag])
+                .args(&paths)
+                .stdout(Stdio::null())
+                .status()
+                .unwrap();
+            total += start.elapsed();
+            assert!(status.success());
+        }
+        println!("{flag}: {:?} per run", total / RUNS);
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
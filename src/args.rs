@@ -1,30 +1,57 @@
 use std::{
+    io::{self, IsTerminal},
     path::{Path, PathBuf},
-    sync::{Arc, Mutex},
+    str::FromStr,
+    sync::{atomic::AtomicBool, Arc, Mutex},
 };
 
-use clap::{ArgGroup, Parser};
+use clap::{ArgGroup, Parser, ValueEnum};
 use ignore::{types::Types, WalkBuilder, WalkParallel};
 use rayon::iter::IterBridge;
-use termcolor::BufferWriter;
+use termcolor::{Buffer, BufferWriter, ColorChoice};
 
 use crate::{
+    dump_tree::LineRange,
     language::SupportedLanguage,
-    printer::StandardBuilder,
+    matcher::LineTerminator,
+    printer::{
+        default_color_specs, ColorSpecs, FormatTemplate, JsonBuilder, NodeTextBuilder,
+        ReplaceTemplate, RewriteBuilder, RewriteMode, Sarif, SarifBuilder, StandardBuilder,
+        SummaryBuilder, SummaryKind, TemplateBuilder, UserColorSpec,
+    },
     project_file_walker::{
         get_project_file_walker_types, into_parallel_iterator, WalkParallelIterator,
     },
-    searcher::{Searcher, SearcherBuilder},
+    searcher::{ConfigError, Encoding, MmapChoice, Searcher, SearcherBuilder},
+    skip::{FileSize, SkipConfig},
     use_printer::Printer,
     NonFatalError,
 };
 
+// between the nodes printed by --node-text, within and across files
+const NODE_TEXT_SEPARATOR: &[u8] = b"--";
+
+// beyond this many explicitly given files, memory maps are no longer used by
+// default (the same heuristic as ripgrep's)
+const MMAP_MAX_PATHS: usize = 10;
+
 #[derive(Parser)]
 #[clap(group(
     ArgGroup::new("query_or_filter")
         .multiple(true)
         .required(true)
-        .args(&["path_to_query_file", "query_text", "filter"])
+        .args(&[
+            "path_to_query_file",
+            "query_text",
+            "filter",
+            "new_parse_errors_since",
+            "dump_tree",
+        ])
+))]
+#[clap(group(
+    ArgGroup::new("rewrite_mode")
+        .args(&["write", "diff"])
+        .requires("rewrite")
 ))]
 pub struct Args {
     paths: Vec<PathBuf>,
@@ -45,9 +72,25 @@ pub struct Args {
     /// matching nodes will be output.
     ///
     /// By default this is the "first" capture encountered in the query source
-    /// text.
-    #[arg(short, long = "capture")]
-    pub capture_name: Option<String>,
+    /// text. This flag may be provided multiple times to output the matching
+    /// nodes of each of those captures, in which case --vimgrep labels each
+    /// line with the name of its capture.
+    #[arg(short, long = "capture", value_name = "CAPTURE_NAME")]
+    pub capture_names: Vec<String>,
+
+    /// Output the matching nodes of every capture in the query.
+    ///
+    /// Like when providing --capture multiple times, --vimgrep labels each line
+    /// with the name of its capture.
+    #[arg(long, conflicts_with = "capture_names")]
+    pub all_captures: bool,
+
+    /// Output the nodes captured by a quantified capture (eg
+    /// "(use_declaration)+ @uses") as a single match spanning all of them.
+    ///
+    /// By default each of those nodes is output as a separate match.
+    #[arg(long)]
+    pub span_quantified_captures: bool,
 
     /// The target language for matching.
     ///
@@ -107,6 +150,447 @@ pub struct Args {
     /// the offset of the matching part itself.
     #[arg(short = 'b', long)]
     pub byte_offset: bool,
+
+    /// Print results in a JSON Lines format.
+    ///
+    /// Each matching node is emitted as its own "match" message including its
+    /// path, language, node kind, capture name, text and (0-based) start/end
+    /// byte, row and column, surrounded by "begin"/"end" messages per file and
+    /// followed by a final "summary" message.
+    #[arg(
+        long,
+        conflicts_with_all = ["vimgrep", "only_matching", "byte_offset"]
+    )]
+    pub json: bool,
+
+    /// When to use colors in the output.
+    ///
+    /// With "auto", colors are only used when printing to a terminal. "ansi"
+    /// is like "always" but uses ANSI escape sequences even on Windows.
+    #[arg(long, value_enum, value_name = "WHEN", default_value = "auto")]
+    pub color: ColorWhen,
+
+    /// Customize the colors used in the output.
+    ///
+    /// Specs have the form "{type}:{attribute}:{value}", where {type} is one
+    /// of path, line, column or match, {attribute} is one of fg, bg or style
+    /// and {value} is a color (for fg/bg) or a style (for style). Eg
+    /// "path:fg:magenta" or "match:style:bold". "{type}:none" clears the
+    /// colors for that type.
+    ///
+    /// This flag may be provided multiple times, later specs overriding
+    /// earlier ones.
+    #[arg(long = "colors", value_name = "COLOR_SPEC")]
+    pub color_specs: Vec<UserColorSpec>,
+
+    /// Print the file path above clusters of matches from each file instead of
+    /// at the start of each matching line, separating the matches from
+    /// different files by an empty line.
+    ///
+    /// This is the default when printing to a terminal. It can be disabled
+    /// with --no-heading.
+    #[arg(long, overrides_with = "no_heading")]
+    heading: bool,
+
+    /// Don't group matches by each file path, instead printing the file path
+    /// at the start of each matching line.
+    ///
+    /// This is the default when not printing to a terminal.
+    #[arg(long, overrides_with = "heading")]
+    no_heading: bool,
+
+    /// Only print the number of matching lines for each file, instead of the
+    /// matches themselves.
+    ///
+    /// Lines spanned by a multi-line matching node are each counted. Files
+    /// with no matches are not printed.
+    #[arg(
+        long,
+        overrides_with_all = ["count_matches", "files_with_matches", "files_without_match"],
+        conflicts_with_all = ["json", "vimgrep", "only_matching", "byte_offset"]
+    )]
+    pub count: bool,
+
+    /// Only print the number of matching nodes for each file, instead of the
+    /// matches themselves.
+    ///
+    /// Unlike --count, each matching node is counted once regardless of how
+    /// many lines it spans or how many other matching nodes share its lines.
+    #[arg(
+        long,
+        overrides_with_all = ["count", "files_with_matches", "files_without_match"],
+        conflicts_with_all = ["json", "vimgrep", "only_matching", "byte_offset"]
+    )]
+    pub count_matches: bool,
+
+    /// Only print the paths of files containing at least one match.
+    ///
+    /// Searching a file stops as soon as its first match is found.
+    #[arg(
+        long,
+        overrides_with_all = ["count", "count_matches", "files_without_match"],
+        conflicts_with_all = ["json", "vimgrep", "only_matching", "byte_offset"]
+    )]
+    pub files_with_matches: bool,
+
+    /// Only print the paths of searched files that don't contain any matches.
+    ///
+    /// Searching a file stops as soon as its first match is found.
+    #[arg(
+        long,
+        overrides_with_all = ["count", "count_matches", "files_with_matches"],
+        conflicts_with_all = ["json", "vimgrep", "only_matching", "byte_offset"]
+    )]
+    pub files_without_match: bool,
+
+    /// Print aggregate statistics about the search once it's finished.
+    ///
+    /// These include the number of matches, files searched and bytes
+    /// searched, as well as how much of the time was spent parsing files
+    /// versus running the query against the parsed trees.
+    ///
+    /// With --json, these statistics are always included in the final
+    /// "summary" message.
+    #[arg(long)]
+    pub stats: bool,
+
+    /// Limit the number of matches printed per file to NUM.
+    ///
+    /// Matching nodes that share lines count as a single match.
+    #[arg(short = 'm', long = "max-count", value_name = "NUM")]
+    pub max_count: Option<u64>,
+
+    /// Limit the total number of matches printed across all files to NUM.
+    ///
+    /// Unlike with -m/--max-count, each matching node counts as a separate
+    /// match. Once NUM matches have been printed, no further files are
+    /// searched. Which matches get printed is not deterministic when
+    /// searching multiple files.
+    #[arg(long, value_name = "NUM", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_total: Option<u64>,
+
+    /// Don't print lines longer than NUM bytes.
+    ///
+    /// Such lines are replaced by a message indicating that they were
+    /// omitted, unless --max-columns-preview is also given.
+    #[arg(short = 'M', long, value_name = "NUM")]
+    pub max_columns: Option<u64>,
+
+    /// Print a preview of lines exceeding --max-columns instead of omitting
+    /// them.
+    ///
+    /// The preview is the first NUM graphemes of the line, or a window of NUM
+    /// graphemes leading up to and including the first matching node on the
+    /// line when that node starts further along.
+    #[arg(long, requires = "max_columns")]
+    pub max_columns_preview: bool,
+
+    /// Follow each printed file path with a NUL byte instead of the usual
+    /// newline or ":" separator.
+    ///
+    /// This is useful for piping paths to eg `xargs -0`.
+    #[arg(short = '0', long)]
+    pub null: bool,
+
+    /// Print the exact text of each matching node followed by a NUL byte,
+    /// without any file paths, line numbers or line framing.
+    ///
+    /// Matching nodes spanning multiple lines are printed intact, which makes
+    /// this suitable for piping to eg `xargs -0`.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "json", "vimgrep", "only_matching", "byte_offset", "count", "count_matches",
+            "files_with_matches", "files_without_match", "max_columns"
+        ]
+    )]
+    pub print0_matches: bool,
+
+    /// Print the exact text of each matching node, preceded by a
+    /// "path:line:column:" header line.
+    ///
+    /// Unlike the default line-oriented output, matching nodes are neither
+    /// widened to whole lines nor clipped to the matching lines. Consecutive
+    /// nodes are separated by a "--" line.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "json", "vimgrep", "only_matching", "byte_offset", "count", "count_matches",
+            "files_with_matches", "files_without_match", "max_columns", "null", "print0_matches"
+        ]
+    )]
+    pub node_text: bool,
+
+    /// Print results in an alternative output format.
+    ///
+    /// With "sarif", a single SARIF 2.1.0 log containing a result for each
+    /// matching node is printed once every file has been searched. Each
+    /// result's rule id is the name of the query file (without extension) if
+    /// --query-file was given, otherwise the name of the capture.
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        conflicts_with_all = [
+            "json", "vimgrep", "only_matching", "byte_offset", "count", "count_matches",
+            "files_with_matches", "files_without_match", "stats", "max_columns", "null",
+            "print0_matches", "node_text"
+        ]
+    )]
+    pub format: Option<OutputFormat>,
+
+    /// The message attached to each result when using --format sarif.
+    ///
+    /// By default the message names the kind of the matching node.
+    #[arg(long, value_name = "MESSAGE", requires = "format")]
+    pub sarif_message: Option<String>,
+
+    /// Print a line rendered from TEMPLATE for each matching node.
+    ///
+    /// Placeholders are {path}, {language}, {kind} (the node kind),
+    /// {capture} (the capture name), {text}, {start_line}, {end_line},
+    /// {start_column}, {end_column}, {start_byte} and {end_byte}. Lines and
+    /// columns are 1-based, bytes are 0-based. {@NAME} is replaced by the
+    /// text of the node captured as NAME by the same query match, or by
+    /// nothing if that capture didn't match anything. Use "{{" and "}}" for
+    /// literal braces.
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = [
+            "json", "vimgrep", "only_matching", "byte_offset", "count", "count_matches",
+            "files_with_matches", "files_without_match", "max_columns", "null",
+            "print0_matches", "node_text", "format"
+        ]
+    )]
+    pub format_template: Option<FormatTemplate>,
+
+    /// Print each matching line with every matching node replaced by
+    /// TEMPLATE.
+    ///
+    /// TEMPLATE may reference the text of other nodes captured by the same
+    /// query match as $NAME or ${NAME}, where NAME is the capture name
+    /// (without leading "@"). Use "$$" for a literal "$". Files are never
+    /// modified.
+    #[arg(
+        short = 'r',
+        long = "replace",
+        value_name = "TEMPLATE",
+        conflicts_with_all = [
+            "json", "count", "count_matches", "files_with_matches", "files_without_match",
+            "print0_matches", "node_text", "format", "format_template"
+        ]
+    )]
+    pub replacement: Option<ReplaceTemplate>,
+
+    /// Rewrite every matching node to TEMPLATE, which uses the same syntax as
+    /// -r/--replace.
+    ///
+    /// This requires either --write or --diff. Files containing overlapping
+    /// matching nodes are not rewritten and reported as errors instead.
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        requires = "rewrite_mode",
+        conflicts_with_all = [
+            "json", "vimgrep", "only_matching", "byte_offset", "count", "count_matches",
+            "files_with_matches", "files_without_match", "stats", "max_columns", "null",
+            "print0_matches", "node_text", "format", "format_template", "replacement"
+        ]
+    )]
+    pub rewrite: Option<ReplaceTemplate>,
+
+    /// Apply --rewrite to the matching files in place.
+    #[arg(long, conflicts_with = "diff")]
+    pub write: bool,
+
+    /// Print the changes --rewrite would make as a unified diff instead of
+    /// applying them.
+    #[arg(long, conflicts_with = "write")]
+    pub diff: bool,
+
+    /// Interactively review rewriting every matching node to TEMPLATE, which
+    /// uses the same syntax as -r/--replace.
+    ///
+    /// Once every file has been searched, each proposed rewrite is shown as a
+    /// diff hunk, one file at a time. Answer "y" to rewrite it, "n" to skip
+    /// it, "e" to type a different replacement, "a" to rewrite it and all the
+    /// remaining ones in the file, or "q" to stop reviewing. The accepted
+    /// rewrites of each file are applied in place after its review.
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = [
+            "json", "vimgrep", "only_matching", "byte_offset", "count", "count_matches",
+            "files_with_matches", "files_without_match", "stats", "max_columns", "null",
+            "print0_matches", "node_text", "format", "format_template", "replacement", "rewrite"
+        ]
+    )]
+    pub interactive: Option<ReplaceTemplate>,
+
+    /// Instead of running a query, report the ERROR and MISSING nodes of each
+    /// searched file that don't exist in its version at the git revision REV.
+    ///
+    /// Each file is parsed as both versions with the same grammar, and the
+    /// git objects are read from the local repository. The exit status is 2
+    /// when any new parse errors are found.
+    #[arg(
+        long,
+        value_name = "REV",
+        conflicts_with_all = [
+            "path_to_query_file", "query_text", "filter", "filter_arg", "capture_names",
+            "all_captures", "span_quantified_captures", "json", "vimgrep", "only_matching",
+            "byte_offset", "count", "count_matches", "files_with_matches", "files_without_match",
+            "stats", "max_columns", "null", "print0_matches", "node_text", "format",
+            "format_template", "replacement", "rewrite", "interactive", "print_sexp", "dump_tree"
+        ]
+    )]
+    pub new_parse_errors_since: Option<String>,
+
+    /// Print the tree-sitter S-expression of each matching node (including
+    /// field names) beneath its match.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "json", "count", "count_matches", "files_with_matches", "files_without_match",
+            "print0_matches", "node_text", "format", "format_template", "rewrite", "interactive"
+        ]
+    )]
+    pub print_sexp: bool,
+
+    /// Limit the S-expressions printed by --print-sexp to NUM levels of
+    /// descendants of each matching node.
+    ///
+    /// Deeper descendants are elided as "...".
+    #[arg(long, value_name = "NUM", requires = "print_sexp")]
+    pub sexp_depth: Option<usize>,
+
+    /// Instead of running a query, print the syntax tree of each searched
+    /// file.
+    ///
+    /// Every named and anonymous node is shown, along with its field name,
+    /// byte range and (1-based) start and end line:column. Files are parsed
+    /// with the same grammars that are used for searching.
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "path_to_query_file", "query_text", "filter", "filter_arg", "capture_names",
+            "all_captures", "span_quantified_captures", "json", "vimgrep", "only_matching",
+            "byte_offset", "count", "count_matches", "files_with_matches", "files_without_match",
+            "stats", "max_columns", "null", "print0_matches", "node_text", "format",
+            "format_template", "replacement", "rewrite", "interactive", "print_sexp"
+        ]
+    )]
+    pub dump_tree: bool,
+
+    /// Only print the nodes of --dump-tree that overlap lines L1 through L2
+    /// (inclusive), or just line L1 when given without "-L2".
+    #[arg(long = "range", value_name = "L1-L2", requires = "dump_tree")]
+    pub dump_range: Option<LineRange>,
+
+    /// Search standard input, reporting it as PATH and inferring its language
+    /// from PATH's file type unless --language is given.
+    ///
+    /// Standard input is also searched, reported as "<stdin>", when "-" is the
+    /// only path given. Either this option or --language is needed to know
+    /// which language standard input is in.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["rewrite", "interactive", "new_parse_errors_since"]
+    )]
+    pub stdin_filename: Option<PathBuf>,
+
+    /// Search files through memory maps instead of reading them on to the
+    /// heap.
+    ///
+    /// By default, memory maps are only used when searching a few explicitly
+    /// given files, since setting one up costs more than reading a small file
+    /// does. Files that are being rewritten are never memory mapped. This can
+    /// be disabled with --no-mmap.
+    #[arg(long, overrides_with = "no_mmap")]
+    mmap: bool,
+
+    /// Never search files through memory maps.
+    #[arg(long, overrides_with = "mmap")]
+    no_mmap: bool,
+
+    /// Skip files that are larger than SIZE bytes.
+    ///
+    /// SIZE can be given with a K, M or G suffix, eg 500K or 10M.
+    #[arg(long, value_name = "SIZE")]
+    max_filesize: Option<FileSize>,
+
+    /// Search files that look binary instead of skipping them.
+    ///
+    /// By default, files with a NUL byte near their start are considered
+    /// binary and skipped.
+    #[arg(long)]
+    text: bool,
+
+    /// Skip files that look minified, ie whose lines near their start are
+    /// very long on average.
+    #[arg(long)]
+    skip_minified: bool,
+
+    /// Report each file that was skipped without being searched, because it
+    /// looked binary or minified or was larger than --max-filesize.
+    #[arg(long)]
+    pub verbose: bool,
+
+    /// Transcode searched files from ENCODING to UTF-8.
+    ///
+    /// ENCODING can be any label from the Encoding Standard, eg
+    /// windows-1252, shift_jis or utf-16le, or `none` to search the raw
+    /// bytes of files. Byte offsets and columns are still reported relative
+    /// to the original files. Files that start with a BOM are transcoded
+    /// according to it, unless ENCODING is `none`.
+    #[arg(
+        short = 'E',
+        long,
+        value_name = "ENCODING",
+        conflicts_with_all = ["rewrite", "interactive"]
+    )]
+    encoding: Option<EncodingChoice>,
+
+    /// Treat CRLF (\r\n) as a line terminator, as well as a plain \n.
+    ///
+    /// The \r of each CRLF line terminator is then left out of printed lines
+    /// and -o/--only-matching fragments, which are always terminated with a
+    /// plain \n.
+    #[arg(long)]
+    crlf: bool,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ColorWhen {
+    Never,
+    Auto,
+    Always,
+    Ansi,
+}
+
+/// A parsed -E/--encoding.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EncodingChoice {
+    Raw,
+    Transcode(Encoding),
+}
+
+impl FromStr for EncodingChoice {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<EncodingChoice, ConfigError> {
+        if s.eq_ignore_ascii_case("none") {
+            return Ok(EncodingChoice::Raw);
+        }
+        Encoding::new(s).map(EncodingChoice::Transcode)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Sarif,
 }
 
 impl Args {
@@ -122,6 +606,26 @@ impl Args {
         self.paths.is_empty()
     }
 
+    /// Whether standard input is searched instead of walking paths: when "-"
+    /// is the only path, or when no paths are given along with
+    /// --stdin-filename.
+    pub(crate) fn searches_stdin(&self) -> bool {
+        if self.new_parse_errors_since.is_some() {
+            return false;
+        }
+        match &*self.paths {
+            [path] => path == Path::new("-"),
+            [] => self.stdin_filename.is_some(),
+            _ => false,
+        }
+    }
+
+    pub(crate) fn stdin_path(&self) -> &Path {
+        self.stdin_filename
+            .as_deref()
+            .unwrap_or(Path::new("<stdin>"))
+    }
+
     fn line_number(&self) -> bool {
         true
     }
@@ -138,7 +642,60 @@ impl Args {
         self.vimgrep
     }
 
+    fn capture_labels(&self) -> bool {
+        self.vimgrep && (self.capture_names.len() > 1 || self.all_captures)
+    }
+
+    fn summary_kind(&self) -> Option<SummaryKind> {
+        if self.files_with_matches {
+            Some(SummaryKind::PathWithMatch)
+        } else if self.files_without_match {
+            Some(SummaryKind::PathWithoutMatch)
+        } else if self.count_matches {
+            Some(SummaryKind::CountMatches)
+        } else if self.count {
+            Some(SummaryKind::Count)
+        } else {
+            None
+        }
+    }
+
+    fn heading(&self) -> bool {
+        if self.no_heading
+            || self.vimgrep
+            || self.json
+            || self.print0_matches
+            || self.node_text
+            || self.format.is_some()
+            || self.format_template.is_some()
+            || self.rewrite.is_some()
+            || self.interactive.is_some()
+            || self.summary_kind().is_some()
+        {
+            false
+        } else {
+            self.heading || io::stdout().is_terminal()
+        }
+    }
+
+    pub(crate) fn file_separator(&self) -> Option<Vec<u8>> {
+        if self.node_text {
+            return Some(NODE_TEXT_SEPARATOR.to_vec());
+        }
+        self.heading().then(|| b"".to_vec())
+    }
+
     fn contexts(&self) -> (usize, usize) {
+        if self.summary_kind().is_some()
+            || self.print0_matches
+            || self.node_text
+            || self.format.is_some()
+            || self.format_template.is_some()
+            || self.rewrite.is_some()
+            || self.interactive.is_some()
+        {
+            return (0, 0);
+        }
         let both = self.context.unwrap_or(0);
         if both > 0 {
             (both, both)
@@ -150,25 +707,214 @@ impl Args {
         }
     }
 
+    pub(crate) fn color_choice(&self) -> ColorChoice {
+        match self.color {
+            ColorWhen::Never => ColorChoice::Never,
+            ColorWhen::Auto => {
+                if io::stdout().is_terminal() {
+                    ColorChoice::Auto
+                } else {
+                    ColorChoice::Never
+                }
+            }
+            ColorWhen::Always => ColorChoice::Always,
+            ColorWhen::Ansi => ColorChoice::AlwaysAnsi,
+        }
+    }
+
+    fn color_specs(&self) -> ColorSpecs {
+        let mut specs = default_color_specs();
+        specs.extend(self.color_specs.iter().cloned());
+        ColorSpecs::new(&specs)
+    }
+
     pub(crate) fn get_searcher(&self) -> Searcher {
         let (before_context, after_context) = self.contexts();
         SearcherBuilder::new()
             .line_number(self.line_number())
             .before_context(before_context)
             .after_context(after_context)
+            .stop_on_first_match(
+                self.summary_kind()
+                    .is_some_and(|summary_kind| summary_kind.quit_early()),
+            )
+            .span_quantified_captures(self.span_quantified_captures)
+            .captures(self.referenced_capture_names().next().is_some())
+            .sexps(self.print_sexp)
+            .sexp_depth(self.sexp_depth)
+            .memory_map(self.mmap_choice())
+            .encoding(match self.encoding {
+                Some(EncodingChoice::Transcode(encoding)) => Some(encoding),
+                _ => None,
+            })
+            .bom_sniffing(self.bom_sniffing())
+            .line_terminator(if self.crlf {
+                LineTerminator::crlf()
+            } else {
+                LineTerminator::default()
+            })
+            .skip(self.skip_config())
             .build()
     }
 
-    pub(crate) fn get_printer(&self, buffer_writer: &BufferWriter) -> Printer {
-        StandardBuilder::new()
-            .per_match(self.per_match())
-            .per_match_one_line(self.per_match_one_line())
-            .column(self.column())
-            .only_matching(self.only_matching)
-            .byte_offset(self.byte_offset)
+    fn skip_config(&self) -> SkipConfig {
+        SkipConfig {
+            max_filesize: self.max_filesize,
+            // text in eg UTF-16 is full of NUL bytes
+            binary: !self.text
+                && !matches!(
+                    self.encoding,
+                    Some(EncodingChoice::Transcode(encoding)) if !encoding.is_ascii_compatible()
+                ),
+            minified: self.skip_minified,
+            bom_sniffing: self.bom_sniffing(),
+        }
+    }
+
+    fn bom_sniffing(&self) -> bool {
+        self.encoding != Some(EncodingChoice::Raw)
+    }
+
+    fn mmap_choice(&self) -> MmapChoice {
+        if self.no_mmap || self.rewrite.is_some() || self.interactive.is_some() {
+            return MmapChoice::never();
+        }
+        if self.mmap
+            || (!self.is_using_default_paths()
+                && self.paths.len() <= MMAP_MAX_PATHS
+                && self.paths.iter().all(|path| path.is_file()))
+        {
+            // SAFETY: a file that's modified while being searched can at worst
+            // produce garbled matches or abort the process, which is the same
+            // trade-off ripgrep makes
+            unsafe { MmapChoice::auto() }
+        } else {
+            MmapChoice::never()
+        }
+    }
+
+    /// The capture names referenced by --format-template, -r/--replace,
+    /// --rewrite or --interactive, which must exist in the query.
+    pub(crate) fn referenced_capture_names(&self) -> impl Iterator<Item = &str> {
+        self.format_template
+            .iter()
+            .flat_map(FormatTemplate::capture_names)
+            .chain(
+                self.replacement
+                    .iter()
+                    .chain(&self.rewrite)
+                    .chain(&self.interactive)
+                    .flat_map(ReplaceTemplate::capture_names),
+            )
+    }
+
+    fn path_terminator(&self) -> Option<u8> {
+        self.null.then_some(b'\0')
+    }
+
+    fn sarif_rule_id(&self) -> Option<String> {
+        self.path_to_query_file
+            .as_ref()
+            .and_then(|path_to_query_file| path_to_query_file.file_stem())
+            .map(|file_stem| file_stem.to_string_lossy().into_owned())
+    }
+
+    pub(crate) fn get_sarif_printer(&self, buffer_writer: &BufferWriter) -> Sarif<Buffer> {
+        SarifBuilder::new()
+            .rule_id(self.sarif_rule_id())
+            .message(self.sarif_message.clone())
+            .max_matches(self.max_count)
             .build(buffer_writer.buffer())
     }
 
+    pub(crate) fn get_printer(&self, buffer_writer: &BufferWriter) -> Printer {
+        if self.json {
+            return Printer::Json(
+                JsonBuilder::new()
+                    .max_matches(self.max_count)
+                    .build(buffer_writer.buffer()),
+            );
+        }
+        if self.format == Some(OutputFormat::Sarif) {
+            return Printer::Sarif(self.get_sarif_printer(buffer_writer));
+        }
+        if let Some(rewrite) = self.rewrite.as_ref() {
+            let mode = if self.write {
+                RewriteMode::Write
+            } else {
+                RewriteMode::Diff
+            };
+            return Printer::Rewrite(
+                RewriteBuilder::new(rewrite.clone(), mode)
+                    .max_matches(self.max_count)
+                    .build(buffer_writer.buffer()),
+            );
+        }
+        if let Some(interactive) = self.interactive.as_ref() {
+            return Printer::Rewrite(
+                RewriteBuilder::new(interactive.clone(), RewriteMode::Interactive)
+                    .max_matches(self.max_count)
+                    .build(buffer_writer.buffer()),
+            );
+        }
+        if let Some(format_template) = self.format_template.as_ref() {
+            return Printer::Template(
+                TemplateBuilder::new(format_template.clone())
+                    .stats(self.stats)
+                    .max_matches(self.max_count)
+                    .build(buffer_writer.buffer()),
+            );
+        }
+        if self.print0_matches {
+            return Printer::NodeText(
+                NodeTextBuilder::new()
+                    .terminator(b'\0')
+                    .stats(self.stats)
+                    .max_matches(self.max_count)
+                    .build(buffer_writer.buffer()),
+            );
+        }
+        if self.node_text {
+            return Printer::NodeText(
+                NodeTextBuilder::new()
+                    .header(true)
+                    .separator(Some(NODE_TEXT_SEPARATOR.to_vec()))
+                    .stats(self.stats)
+                    .max_matches(self.max_count)
+                    .build(buffer_writer.buffer()),
+            );
+        }
+        if let Some(summary_kind) = self.summary_kind() {
+            return Printer::Summary(
+                SummaryBuilder::new()
+                    .kind(summary_kind)
+                    .color_specs(self.color_specs())
+                    .stats(self.stats)
+                    .max_matches(self.max_count)
+                    .path_terminator(self.path_terminator())
+                    .build(buffer_writer.buffer()),
+            );
+        }
+        Printer::Standard(
+            StandardBuilder::new()
+                .color_specs(self.color_specs())
+                .heading(self.heading())
+                .stats(self.stats)
+                .max_matches(self.max_count)
+                .max_columns(self.max_columns)
+                .max_columns_preview(self.max_columns_preview)
+                .per_match(self.per_match())
+                .per_match_one_line(self.per_match_one_line())
+                .column(self.column())
+                .capture_labels(self.capture_labels())
+                .only_matching(self.only_matching)
+                .byte_offset(self.byte_offset)
+                .replacement(self.replacement.clone())
+                .path_terminator(self.path_terminator())
+                .build(buffer_writer.buffer()),
+        )
+    }
+
     pub(crate) fn get_project_file_walker_types(&self) -> Types {
         get_project_file_walker_types(self.language)
     }
@@ -187,7 +933,51 @@ impl Args {
     pub(crate) fn get_project_file_parallel_iterator(
         &self,
         non_fatal_errors: Arc<Mutex<Vec<NonFatalError>>>,
+        quit: Arc<AtomicBool>,
     ) -> IterBridge<WalkParallelIterator> {
-        into_parallel_iterator(self.get_project_file_walker(), non_fatal_errors)
+        into_parallel_iterator(self.get_project_file_walker(), non_fatal_errors, quit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uses_mmap(args: &[&str]) -> bool {
+        let args = ["tree-sitter-grep", "-q", "(function_item) @function_item"]
+            .into_iter()
+            .chain(args.iter().copied());
+        Args::try_parse_from(args)
+            .unwrap()
+            .mmap_choice()
+            .is_enabled()
+    }
+
+    #[test]
+    fn test_mmap_choice_few_explicit_files() {
+        assert!(uses_mmap(&["src/lib.rs", "src/args.rs"]));
+    }
+
+    #[test]
+    fn test_mmap_choice_default_paths() {
+        assert!(!uses_mmap(&[]));
+    }
+
+    #[test]
+    fn test_mmap_choice_directory() {
+        assert!(!uses_mmap(&["src/lib.rs", "src"]));
+    }
+
+    #[test]
+    fn test_mmap_choice_too_many_files() {
+        assert!(!uses_mmap(&["src/lib.rs"; MMAP_MAX_PATHS + 1]));
+    }
+
+    #[test]
+    fn test_mmap_choice_flags() {
+        assert!(uses_mmap(&["--mmap", "src"]));
+        assert!(!uses_mmap(&["--no-mmap", "src/lib.rs"]));
+        assert!(!uses_mmap(&["--mmap", "--no-mmap", "src/lib.rs"]));
+        assert!(!uses_mmap(&["--rewrite", "f", "--diff", "src/lib.rs"]));
     }
 }
@@ -1,7 +1,7 @@
 use std::process;
 
 use clap::Parser;
-use tree_sitter_grep::{run, Args, RunStatus};
+use tree_sitter_grep::{run, Args, NonFatalError, RunStatus};
 
 pub fn main() {
     let args = Args::parse();
@@ -10,10 +10,10 @@ pub fn main() {
             non_fatal_errors,
             matched,
         }) => {
-            if !non_fatal_errors.is_empty() {
-                for non_fatal_error in non_fatal_errors {
-                    eprintln!("{non_fatal_error}");
-                }
+            for non_fatal_error in &non_fatal_errors {
+                eprintln!("{non_fatal_error}");
+            }
+            if !non_fatal_errors.iter().all(NonFatalError::is_informational) {
                 exit(ExitCode::Error);
             } else if matched {
                 exit(ExitCode::Success);
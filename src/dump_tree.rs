@@ -0,0 +1,172 @@
+use std::{
+    error, fmt, fs,
+    io::{self, Read, Write},
+    path::Path,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use termcolor::BufferWriter;
+use tree_sitter::{Node, TreeCursor};
+
+use crate::{
+    for_each_project_file, format_relative_path, single_language_for_file, treesitter::get_parser,
+    Args, Error, Input, NonFatalError, RunStatus, SingleFileSearchNonFailure,
+};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LineRangeError {
+    Invalid(String),
+}
+
+impl error::Error for LineRangeError {}
+
+impl fmt::Display for LineRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            LineRangeError::Invalid(ref original) => write!(
+                f,
+                "invalid line range '{}'. Expected eg '3-10' or '3'.",
+                original,
+            ),
+        }
+    }
+}
+
+/// A parsed --range, eg `3-10`: 1-based and inclusive.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LineRange {
+    start: usize,
+    end: usize,
+}
+
+impl LineRange {
+    fn overlaps(&self, node: &Node) -> bool {
+        node.start_position().row < self.end && node.end_position().row + 1 >= self.start
+    }
+}
+
+impl FromStr for LineRange {
+    type Err = LineRangeError;
+
+    fn from_str(s: &str) -> Result<LineRange, LineRangeError> {
+        let invalid = || LineRangeError::Invalid(s.to_owned());
+        let (start, end) = s.split_once('-').unwrap_or((s, s));
+        let start: usize = start.parse().map_err(|_| invalid())?;
+        let end: usize = end.parse().map_err(|_| invalid())?;
+        if start == 0 || end < start {
+            return Err(invalid());
+        }
+        Ok(LineRange { start, end })
+    }
+}
+
+/// Prints the syntax tree of each searched file, parsed with the grammar of
+/// its language.
+pub(crate) fn run(args: &Args) -> Result<RunStatus, Error> {
+    let buffer_writer = BufferWriter::stdout(args.color_choice());
+    let searched = AtomicBool::new(false);
+    let non_fatal_errors: Arc<Mutex<Vec<NonFatalError>>> = Default::default();
+
+    for_each_project_file(
+        args,
+        non_fatal_errors.clone(),
+        |path, input, matched_languages| {
+            searched.store(true, Ordering::SeqCst);
+            let language = single_language_for_file(args, path, matched_languages)?;
+            let relative_path = format_relative_path(path, args.is_using_default_paths());
+            let source = match input {
+                Input::Path => fs::read(path),
+                Input::Stdin => {
+                    let mut source = Vec::new();
+                    io::stdin().read_to_end(&mut source).map(|_| source)
+                }
+            }
+            .map_err(|error| NonFatalError::ReadError {
+                path: relative_path.to_owned(),
+                message: error.to_string(),
+            })?;
+            let tree = get_parser(language.language())
+                .parse(&source, None)
+                .unwrap();
+            let mut buffer = buffer_writer.buffer();
+            write_tree(
+                &mut buffer,
+                relative_path,
+                &mut tree.walk(),
+                args.dump_range,
+            )
+            .unwrap();
+            buffer_writer.print(&buffer).unwrap();
+            Ok(SingleFileSearchNonFailure::RanQuery)
+        },
+    )?;
+
+    let mut non_fatal_errors = non_fatal_errors.lock().unwrap().clone();
+    let searched = searched.load(Ordering::SeqCst);
+    if !searched {
+        non_fatal_errors.push(NonFatalError::NothingSearched);
+    }
+    Ok(RunStatus {
+        matched: searched,
+        non_fatal_errors,
+    })
+}
+
+fn write_tree<W: Write>(
+    wtr: &mut W,
+    path: &Path,
+    cursor: &mut TreeCursor,
+    range: Option<LineRange>,
+) -> io::Result<()> {
+    writeln!(wtr, "{}", path.display())?;
+    write_node(wtr, cursor, 1, range)
+}
+
+fn write_node<W: Write>(
+    wtr: &mut W,
+    cursor: &mut TreeCursor,
+    depth: usize,
+    range: Option<LineRange>,
+) -> io::Result<()> {
+    let node = cursor.node();
+    if range.is_some_and(|range| !range.overlaps(&node)) {
+        return Ok(());
+    }
+    write!(wtr, "{:indent$}", "", indent = depth * 2)?;
+    if let Some(field_name) = cursor.field_name() {
+        write!(wtr, "{field_name}: ")?;
+    }
+    if node.is_missing() {
+        write!(wtr, "MISSING ")?;
+    }
+    if node.is_named() {
+        write!(wtr, "{}", node.kind())?;
+    } else {
+        write!(wtr, "{:?}", node.kind())?;
+    }
+    let (start_point, end_point) = (node.start_position(), node.end_position());
+    writeln!(
+        wtr,
+        " [{}..{}] {}:{}-{}:{}",
+        node.start_byte(),
+        node.end_byte(),
+        start_point.row + 1,
+        start_point.column + 1,
+        end_point.row + 1,
+        end_point.column + 1,
+    )?;
+    if cursor.goto_first_child() {
+        loop {
+            write_node(wtr, cursor, depth + 1, range)?;
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+        cursor.goto_parent();
+    }
+    Ok(())
+}
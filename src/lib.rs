@@ -1,43 +1,51 @@
 #![allow(clippy::into_iter_on_ref)]
 
 use std::{
-    fmt, fs, io,
+    fmt, fs,
+    io::{self, Write},
     path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc, Mutex, OnceLock, RwLock,
     },
+    time::{Duration, Instant},
 };
 
-use ignore::DirEntry;
 use plugin::get_loaded_filter;
+use printer::{JsonBuilder, NiceDuration, PendingRewrite, Review, SarifResult, Stats};
+use project_file_walker::get_matched_languages_for_path;
 use rayon::prelude::*;
-use termcolor::{BufferWriter, ColorChoice};
+use termcolor::BufferWriter;
 use thiserror::Error;
 use tree_sitter::{Query, QueryError};
 
 mod args;
+mod dump_tree;
 mod language;
 mod line_buffer;
 mod lines;
 mod macros;
 mod matcher;
+mod parse_errors;
 mod plugin;
 mod printer;
 mod project_file_walker;
 mod query_context;
 mod searcher;
 mod sink;
+mod skip;
 mod treesitter;
 mod use_printer;
 mod use_searcher;
 
 pub use args::Args;
+use args::OutputFormat;
 use language::{BySupportedLanguage, SupportedLanguage};
 pub use plugin::PluginInitializeReturn;
 use query_context::QueryContext;
+use skip::SkipReason;
 use treesitter::maybe_get_query;
-use use_printer::get_printer;
+use use_printer::{get_printer, rewrite_failure_error, MatchBudget};
 use use_searcher::get_searcher;
 
 #[derive(Debug, Error)]
@@ -75,6 +83,16 @@ pub enum Error {
     FilterPluginExpectedArgument,
     #[error("plugin couldn't parse argument {filter_arg:?}")]
     FilterPluginCouldntParseArgument { filter_arg: String },
+    #[error("couldn't run git")]
+    GitCommandError { source: io::Error },
+    #[error("git failed: {message}")]
+    GitError { message: String },
+    #[error("couldn't resolve git revision {revision:?}")]
+    InvalidRevision { revision: String },
+    #[error("searching standard input requires --language or --stdin-filename")]
+    StdinLanguageNotSpecified,
+    #[error("standard input can't be rewritten")]
+    RewriteStdin,
 }
 
 #[derive(Clone, Debug, Error)]
@@ -101,6 +119,35 @@ pub enum NonFatalError {
     },
     #[error("No files were searched")]
     NothingSearched,
+    #[error(
+        "File {path:?} was not rewritten because the matches at \
+         {first_line}:{first_column} and {second_line}:{second_column} overlap"
+    )]
+    OverlappingRewrites {
+        path: PathBuf,
+        first_line: usize,
+        first_column: usize,
+        second_line: usize,
+        second_column: usize,
+    },
+    #[error("File {path:?} was not rewritten because it isn't encoded as UTF-8")]
+    RewriteTranscodedFile { path: PathBuf },
+    #[error("File {path:?} was not rewritten because it was modified during review")]
+    RewriteModifiedFile { path: PathBuf },
+    #[error("Couldn't rewrite file {path:?}: {message}")]
+    RewriteWriteError { path: PathBuf, message: String },
+    #[error("Couldn't read file {path:?}: {message}")]
+    ReadError { path: PathBuf, message: String },
+    #[error("Couldn't read file {path:?} at revision {revision:?}: {message}")]
+    ReadAtRevisionError {
+        path: PathBuf,
+        revision: String,
+        message: String,
+    },
+    #[error("New parse errors were found since revision {revision:?}")]
+    NewParseErrors { revision: String },
+    #[error("File {path:?} was skipped because {reason}")]
+    SkippedFile { path: PathBuf, reason: SkipReason },
     #[error("{error}")]
     IgnoreError {
         #[from]
@@ -108,6 +155,14 @@ pub enum NonFatalError {
     },
 }
 
+impl NonFatalError {
+    /// Whether this only informs about the run (and is only reported with
+    /// --verbose) rather than making it unsuccessful.
+    pub fn is_informational(&self) -> bool {
+        matches!(self, NonFatalError::SkippedFile { .. })
+    }
+}
+
 #[derive(Clone)]
 enum CaptureIndexError {
     NoCaptureInQuery,
@@ -126,25 +181,32 @@ impl From<CaptureIndexError> for Error {
 }
 
 #[derive(Default)]
-struct CaptureIndex(OnceLock<Result<u32, CaptureIndexError>>);
+struct CaptureIndices(OnceLock<Result<Vec<u32>, CaptureIndexError>>);
 
-impl CaptureIndex {
+impl CaptureIndices {
     pub fn get_or_init(
         &self,
         query: &Query,
-        capture_name: Option<&str>,
-    ) -> Result<u32, CaptureIndexError> {
+        capture_names: &[String],
+        all_captures: bool,
+    ) -> Result<Vec<u32>, CaptureIndexError> {
         self.0
-            .get_or_init(|| match capture_name {
-                None => match query.capture_names().len() {
+            .get_or_init(|| match capture_names {
+                [] => match query.capture_names().len() {
                     0 => Err(CaptureIndexError::NoCaptureInQuery),
-                    _ => Ok(0),
+                    capture_count if all_captures => Ok((0..capture_count as u32).collect()),
+                    _ => Ok(vec![0]),
                 },
-                Some(capture_name) => query.capture_index_for_name(capture_name).ok_or_else(|| {
-                    CaptureIndexError::InvalidCaptureName {
-                        capture_name: capture_name.to_owned(),
-                    }
-                }),
+                capture_names => capture_names
+                    .iter()
+                    .map(|capture_name| {
+                        query.capture_index_for_name(capture_name).ok_or_else(|| {
+                            CaptureIndexError::InvalidCaptureName {
+                                capture_name: capture_name.to_owned(),
+                            }
+                        })
+                    })
+                    .collect(),
             })
             .clone()
     }
@@ -240,7 +302,16 @@ impl From<CaptureIndexError> for SingleFileSearchError {
 
 enum SingleFileSearchNonFailure {
     QueryNotParseableForFile,
+    SkippedFile,
     RanQuery,
+    ReachedMaxTotal,
+}
+
+/// Where the contents of a searched file are read from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Input {
+    Path,
+    Stdin,
 }
 
 type SingleFileSearchResult = Result<SingleFileSearchNonFailure, SingleFileSearchError>;
@@ -258,6 +329,15 @@ impl From<NonFatalError> for SingleFileSearchResult {
 }
 
 pub fn run(args: Args) -> Result<RunStatus, Error> {
+    if let Some(revision) = args.new_parse_errors_since.as_deref() {
+        return parse_errors::run(&args, revision);
+    }
+    if args.dump_tree {
+        return dump_tree::run(&args);
+    }
+    if args.searches_stdin() && (args.rewrite.is_some() || args.interactive.is_some()) {
+        return Err(Error::RewriteStdin);
+    }
     let query_text = match (args.path_to_query_file.as_ref(), args.query_text.as_ref()) {
         (Some(path_to_query_file), None) => {
             fs::read_to_string(path_to_query_file).map_err(|source| Error::QueryFileReadError {
@@ -272,8 +352,14 @@ pub fn run(args: Args) -> Result<RunStatus, Error> {
     let filter =
         get_loaded_filter(args.filter.as_deref(), args.filter_arg.as_deref())?.map(Arc::new);
     let cached_queries: CachedQueries = Default::default();
-    let capture_index = CaptureIndex::default();
-    let buffer_writer = BufferWriter::stdout(ColorChoice::Never);
+    let capture_indices = CaptureIndices::default();
+    let mut buffer_writer = BufferWriter::stdout(args.color_choice());
+    buffer_writer.separator(args.file_separator());
+    let start_time = Instant::now();
+    let stats: Mutex<Stats> = Default::default();
+    let sarif_results: Mutex<Vec<SarifResult>> = Default::default();
+    let pending_rewrites: Mutex<Vec<PendingRewrite>> = Default::default();
+    let total_match_budget = args.max_total.map(MatchBudget::new);
     let matched = AtomicBool::new(false);
     let searched = AtomicBool::new(false);
     let non_fatal_errors: Arc<Mutex<Vec<NonFatalError>>> = Default::default();
@@ -281,13 +367,18 @@ pub fn run(args: Args) -> Result<RunStatus, Error> {
     for_each_project_file(
         &args,
         non_fatal_errors.clone(),
-        |project_file_dir_entry, matched_languages| {
-            searched.store(true, Ordering::SeqCst);
+        |path, input, matched_languages| {
+            if total_match_budget
+                .as_ref()
+                .is_some_and(MatchBudget::is_exhausted)
+            {
+                return Ok(SingleFileSearchNonFailure::ReachedMaxTotal);
+            }
             let language = match args.language {
                 Some(specified_language) => {
                     if !matched_languages.contains(&specified_language) {
                         return NonFatalError::ExplicitPathArgumentNotOfSpecifiedType {
-                            path: project_file_dir_entry.path().to_owned(),
+                            path: path.to_owned(),
                             specified_language,
                         }
                         .into();
@@ -297,7 +388,7 @@ pub fn run(args: Args) -> Result<RunStatus, Error> {
                 None => match matched_languages.len() {
                     0 => {
                         return NonFatalError::ExplicitPathArgumentNotOfKnownType {
-                            path: project_file_dir_entry.path().to_owned(),
+                            path: path.to_owned(),
                         }
                         .into();
                     }
@@ -313,12 +404,13 @@ pub fn run(args: Args) -> Result<RunStatus, Error> {
                             .collect::<Vec<_>>();
                         match successfully_parsed_query_languages.len() {
                             0 => {
+                                searched.store(true, Ordering::SeqCst);
                                 return Ok(SingleFileSearchNonFailure::QueryNotParseableForFile);
                             }
                             1 => successfully_parsed_query_languages[0],
                             _ => {
                                 return NonFatalError::AmbiguousLanguageForFile {
-                                    path: project_file_dir_entry.path().to_owned(),
+                                    path: path.to_owned(),
                                     languages: successfully_parsed_query_languages,
                                 }
                                 .into();
@@ -330,34 +422,105 @@ pub fn run(args: Args) -> Result<RunStatus, Error> {
             let query = match cached_queries.get_and_cache_query_for_language(&query_text, language)
             {
                 Some(query) => query,
-                None => return Ok(SingleFileSearchNonFailure::QueryNotParseableForFile),
+                None => {
+                    // so that not being able to parse the query gets reported
+                    // rather than nothing having been searched
+                    searched.store(true, Ordering::SeqCst);
+                    return Ok(SingleFileSearchNonFailure::QueryNotParseableForFile);
+                }
             };
-            let capture_index = capture_index.get_or_init(&query, args.capture_name.as_deref())?;
+            let capture_indices =
+                capture_indices.get_or_init(&query, &args.capture_names, args.all_captures)?;
+            if let Some(capture_name) = args
+                .referenced_capture_names()
+                .find(|&capture_name| query.capture_index_for_name(capture_name).is_none())
+            {
+                return Error::InvalidCaptureName {
+                    capture_name: capture_name.to_owned(),
+                }
+                .into();
+            }
             let printer = get_printer(&buffer_writer, &args);
             let mut printer = printer.borrow_mut();
-            let path =
-                format_relative_path(project_file_dir_entry.path(), args.is_using_default_paths());
+            let path = format_relative_path(path, args.is_using_default_paths());
 
             let query_context =
-                QueryContext::new(query, capture_index, language.language(), filter.clone());
+                QueryContext::new(query, capture_indices, language.language(), filter.clone());
 
             printer.get_mut().clear();
-            let mut sink = printer.sink_with_path(path);
-            get_searcher(&args)
-                .borrow_mut()
-                .search_path(query_context, path, &mut sink)
+            let searcher = get_searcher(&args);
+            let search_result = printer
+                .search_path(
+                    &mut searcher.borrow_mut(),
+                    query_context,
+                    path,
+                    input,
+                    language,
+                    total_match_budget.as_ref(),
+                )
                 .unwrap();
-            if sink.has_match() {
+            if let Some(reason) = searcher.borrow().skip_reason() {
+                if !args.verbose {
+                    return Ok(SingleFileSearchNonFailure::SkippedFile);
+                }
+                return NonFatalError::SkippedFile {
+                    path: path.to_owned(),
+                    reason,
+                }
+                .into();
+            }
+            searched.store(true, Ordering::SeqCst);
+            if search_result.has_match {
                 matched.store(true, Ordering::SeqCst);
             }
+            if let Some(search_stats) = search_result.stats.as_ref() {
+                *stats.lock().unwrap() += search_stats;
+            }
+            sarif_results
+                .lock()
+                .unwrap()
+                .extend(search_result.sarif_results);
+            pending_rewrites
+                .lock()
+                .unwrap()
+                .extend(search_result.pending_rewrite);
             buffer_writer.print(printer.get_mut()).unwrap();
+            if let Some(non_fatal_error) = search_result.non_fatal_error {
+                return non_fatal_error.into();
+            }
 
+            if total_match_budget
+                .as_ref()
+                .is_some_and(MatchBudget::is_exhausted)
+            {
+                return Ok(SingleFileSearchNonFailure::ReachedMaxTotal);
+            }
             Ok(SingleFileSearchNonFailure::RanQuery)
         },
     )?;
 
+    if args.json {
+        let mut json = JsonBuilder::new().build(buffer_writer.buffer());
+        json.write_summary(NiceDuration(start_time.elapsed()), &stats.lock().unwrap())
+            .unwrap();
+        buffer_writer.print(json.get_mut()).unwrap();
+    } else if args.format == Some(OutputFormat::Sarif) {
+        let mut sarif = args.get_sarif_printer(&buffer_writer);
+        sarif
+            .write_log(sarif_results.into_inner().unwrap())
+            .unwrap();
+        buffer_writer.print(sarif.get_mut()).unwrap();
+    } else if args.stats {
+        print_stats(&stats.lock().unwrap(), start_time.elapsed()).unwrap();
+    } else if args.interactive.is_some() {
+        review_pending_rewrites(
+            pending_rewrites.into_inner().unwrap(),
+            &mut non_fatal_errors.lock().unwrap(),
+        );
+    }
+
     let mut non_fatal_errors = non_fatal_errors.lock().unwrap().clone();
-    if non_fatal_errors.is_empty() {
+    if non_fatal_errors.iter().all(NonFatalError::is_informational) {
         if !searched.load(Ordering::SeqCst) {
             non_fatal_errors.push(NonFatalError::NothingSearched);
         } else {
@@ -371,29 +534,93 @@ pub fn run(args: Args) -> Result<RunStatus, Error> {
     })
 }
 
+fn print_stats(stats: &Stats, elapsed_total: Duration) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    write!(
+        stdout,
+        "
+{matches} matches
+{matched_lines} matched lines
+{searches_with_match} files contained matches
+{searches} files searched
+{bytes_printed} bytes printed
+{bytes_searched} bytes searched
+{search_time:0.6} seconds spent searching
+{parse_time:0.6} seconds spent parsing
+{query_time:0.6} seconds spent querying
+{process_time:0.6} seconds
+",
+        matches = stats.matches(),
+        matched_lines = stats.matched_lines(),
+        searches_with_match = stats.searches_with_match(),
+        searches = stats.searches(),
+        bytes_printed = stats.bytes_printed(),
+        bytes_searched = stats.bytes_searched(),
+        search_time = stats.elapsed().as_secs_f64(),
+        parse_time = stats.elapsed_parsing().as_secs_f64(),
+        query_time = stats.elapsed_querying().as_secs_f64(),
+        process_time = elapsed_total.as_secs_f64(),
+    )
+}
+
+fn review_pending_rewrites(
+    mut pending_rewrites: Vec<PendingRewrite>,
+    non_fatal_errors: &mut Vec<NonFatalError>,
+) {
+    pending_rewrites.sort_by(|a, b| a.path().cmp(b.path()));
+    let mut review = Review::new(io::stdin().lock(), io::stdout().lock());
+    for mut pending_rewrite in pending_rewrites {
+        if review.has_quit() {
+            break;
+        }
+        review.review(&mut pending_rewrite).unwrap();
+        if let Err(failure) = pending_rewrite.apply() {
+            non_fatal_errors.push(rewrite_failure_error(pending_rewrite.path(), &failure));
+        }
+    }
+}
+
 fn for_each_project_file(
     args: &Args,
     non_fatal_errors: Arc<Mutex<Vec<NonFatalError>>>,
-    callback: impl Fn(DirEntry, Vec<SupportedLanguage>) -> SingleFileSearchResult + Sync,
+    callback: impl Fn(&Path, Input, Vec<SupportedLanguage>) -> SingleFileSearchResult + Sync,
 ) -> Result<(), Error> {
     let fatal_error: RwLock<Option<Error>> = Default::default();
-    args.get_project_file_parallel_iterator(non_fatal_errors.clone())
-        .for_each(|(project_file_dir_entry, matched_languages)| {
-            if fatal_error.read().unwrap().is_some() {
-                return;
+    let quit: Arc<AtomicBool> = Default::default();
+    let handle_result = |result: SingleFileSearchResult| match result {
+        Ok(SingleFileSearchNonFailure::ReachedMaxTotal) => {
+            quit.store(true, Ordering::SeqCst);
+        }
+        Ok(_) => (),
+        Err(error) => match error {
+            SingleFileSearchError::NonFatalSearchError(error) => {
+                non_fatal_errors.lock().unwrap().push(error);
             }
-
-            if let Err(error) = callback(project_file_dir_entry, matched_languages) {
-                match error {
-                    SingleFileSearchError::NonFatalSearchError(error) => {
-                        non_fatal_errors.lock().unwrap().push(error);
-                    }
-                    SingleFileSearchError::FatalError(error) => {
-                        *fatal_error.write().unwrap() = Some(error);
-                    }
-                }
+            SingleFileSearchError::FatalError(error) => {
+                *fatal_error.write().unwrap() = Some(error);
             }
-        });
+        },
+    };
+    if args.searches_stdin() {
+        handle_result(callback(
+            args.stdin_path(),
+            Input::Stdin,
+            get_stdin_languages(args)?,
+        ));
+    } else {
+        args.get_project_file_parallel_iterator(non_fatal_errors.clone(), quit.clone())
+            .for_each(|(project_file_dir_entry, matched_languages)| {
+                if fatal_error.read().unwrap().is_some() || quit.load(Ordering::SeqCst) {
+                    return;
+                }
+
+                handle_result(callback(
+                    project_file_dir_entry.path(),
+                    Input::Path,
+                    matched_languages,
+                ));
+            });
+    }
 
     match fatal_error.into_inner().unwrap() {
         Some(fatal_error) => Err(fatal_error),
@@ -401,6 +628,46 @@ fn for_each_project_file(
     }
 }
 
+/// The languages standard input could be in, which are then narrowed down just
+/// like the languages matched by a walked file.
+fn get_stdin_languages(args: &Args) -> Result<Vec<SupportedLanguage>, Error> {
+    match (args.stdin_filename.as_deref(), args.language) {
+        (Some(stdin_filename), _) => Ok(get_matched_languages_for_path(stdin_filename)),
+        (None, Some(language)) => Ok(vec![language]),
+        (None, None) => Err(Error::StdinLanguageNotSpecified),
+    }
+}
+
+/// The language to parse `path` as when not running a query, which (unlike a
+/// query) can't disambiguate between multiple matched languages.
+fn single_language_for_file(
+    args: &Args,
+    path: &Path,
+    matched_languages: Vec<SupportedLanguage>,
+) -> Result<SupportedLanguage, NonFatalError> {
+    match args.language {
+        Some(specified_language) => {
+            if !matched_languages.contains(&specified_language) {
+                return Err(NonFatalError::ExplicitPathArgumentNotOfSpecifiedType {
+                    path: path.to_owned(),
+                    specified_language,
+                });
+            }
+            Ok(specified_language)
+        }
+        None => match matched_languages.len() {
+            0 => Err(NonFatalError::ExplicitPathArgumentNotOfKnownType {
+                path: path.to_owned(),
+            }),
+            1 => Ok(matched_languages[0]),
+            _ => Err(NonFatalError::AmbiguousLanguageForFile {
+                path: path.to_owned(),
+                languages: matched_languages,
+            }),
+        },
+    }
+}
+
 fn format_relative_path(path: &Path, is_using_default_paths: bool) -> &Path {
     if is_using_default_paths && path.starts_with("./") {
         path.strip_prefix("./").unwrap()
@@ -116,7 +116,6 @@ impl LineTerminator {
     }
 
     #[inline]
-    #[allow(dead_code)]
     pub fn crlf() -> LineTerminator {
         LineTerminator(LineTerminatorImp::CRLF)
     }
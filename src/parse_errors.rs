@@ -0,0 +1,304 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use termcolor::BufferWriter;
+use tree_sitter::{Node, Parser, Point};
+
+use crate::{
+    for_each_project_file, format_relative_path, language::SupportedLanguage,
+    single_language_for_file, treesitter::get_parser, Args, Error, NonFatalError, RunStatus,
+    SingleFileSearchNonFailure,
+};
+
+/// An `ERROR` or `MISSING` node.
+#[derive(Debug)]
+struct ParseError {
+    start_point: Point,
+    key: ParseErrorKey,
+}
+
+// Positions aren't part of the key since unrelated edits can move an existing
+// parse error around.
+#[derive(Debug, Eq, Hash, PartialEq)]
+struct ParseErrorKey {
+    description: String,
+    text: Vec<u8>,
+}
+
+impl ParseError {
+    fn new(node: Node, source: &[u8]) -> Self {
+        let description = if node.is_missing() {
+            if node.is_named() {
+                format!("MISSING {}", node.kind())
+            } else {
+                format!("MISSING {:?}", node.kind())
+            }
+        } else {
+            "ERROR".to_owned()
+        };
+        Self {
+            start_point: node.start_position(),
+            key: ParseErrorKey {
+                description,
+                text: source[node.byte_range()].to_owned(),
+            },
+        }
+    }
+}
+
+/// Reports the parse errors of each searched file that didn't exist in its
+/// version at `revision`, which is read from the local git repository.
+pub(crate) fn run(args: &Args, revision: &str) -> Result<RunStatus, Error> {
+    let revision_reader = Mutex::new(RevisionReader::new(revision)?);
+
+    let buffer_writer = BufferWriter::stdout(args.color_choice());
+    let found = AtomicBool::new(false);
+    let searched = AtomicBool::new(false);
+    let non_fatal_errors: Arc<Mutex<Vec<NonFatalError>>> = Default::default();
+
+    for_each_project_file(
+        args,
+        non_fatal_errors.clone(),
+        |path, _, matched_languages| {
+            searched.store(true, Ordering::SeqCst);
+            let language = single_language_for_file(args, path, matched_languages)?;
+
+            let relative_path = format_relative_path(path, args.is_using_default_paths());
+            let source = fs::read(path).map_err(|error| NonFatalError::ReadError {
+                path: relative_path.to_owned(),
+                message: error.to_string(),
+            })?;
+            let old_source = revision_reader
+                .lock()
+                .unwrap()
+                .read(path)
+                .map_err(|error| NonFatalError::ReadAtRevisionError {
+                    path: relative_path.to_owned(),
+                    revision: revision.to_owned(),
+                    message: error.to_string(),
+                })?;
+            let new_parse_errors = new_parse_errors(&source, old_source, language);
+            if new_parse_errors.is_empty() {
+                return Ok(SingleFileSearchNonFailure::RanQuery);
+            }
+            found.store(true, Ordering::SeqCst);
+            let mut buffer = buffer_writer.buffer();
+            write_parse_errors(&mut buffer, relative_path, &new_parse_errors).unwrap();
+            buffer_writer.print(&buffer).unwrap();
+            Ok(SingleFileSearchNonFailure::RanQuery)
+        },
+    )?;
+
+    let mut non_fatal_errors = non_fatal_errors.lock().unwrap().clone();
+    if !searched.load(Ordering::SeqCst) {
+        non_fatal_errors.push(NonFatalError::NothingSearched);
+    }
+    if found.load(Ordering::SeqCst) {
+        non_fatal_errors.push(NonFatalError::NewParseErrors {
+            revision: revision.to_owned(),
+        });
+    }
+    Ok(RunStatus {
+        matched: true,
+        non_fatal_errors,
+    })
+}
+
+/// Reads files as of a commit through a single `git cat-file --batch` process.
+struct RevisionReader {
+    commit: String,
+    top_level: PathBuf,
+    process: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl RevisionReader {
+    fn new(revision: &str) -> Result<Self, Error> {
+        let top_level = PathBuf::from(run_git(&["rev-parse", "--show-toplevel"])?);
+        let top_level = fs::canonicalize(&top_level).unwrap_or(top_level);
+        let commit = run_git(&[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{revision}^{{commit}}"),
+        ])
+        .map_err(|_| Error::InvalidRevision {
+            revision: revision.to_owned(),
+        })?;
+        let mut process = Command::new("git")
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|source| Error::GitCommandError { source })?;
+        let stdin = process.stdin.take().unwrap();
+        let stdout = BufReader::new(process.stdout.take().unwrap());
+        Ok(Self {
+            commit,
+            top_level,
+            process,
+            stdin,
+            stdout,
+        })
+    }
+
+    /// `None` when `path` doesn't exist at the commit, eg because it has been
+    /// added since.
+    fn read(&mut self, path: &Path) -> io::Result<Option<Vec<u8>>> {
+        let path = fs::canonicalize(path)?;
+        let Ok(path) = path.strip_prefix(&self.top_level) else {
+            return Ok(None);
+        };
+        let path = path
+            .to_str()
+            .filter(|path| !path.contains('\n'))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "unsupported path"))?;
+        writeln!(
+            self.stdin,
+            "{}:{}",
+            self.commit,
+            path.replace(std::path::MAIN_SEPARATOR, "/")
+        )?;
+        self.stdin.flush()?;
+
+        let mut header = String::new();
+        self.stdout.read_line(&mut header)?;
+        // either "<object> missing" or "<oid> <type> <size>" followed by the
+        // contents and a newline
+        let fields = header.split_whitespace().collect::<Vec<_>>();
+        match fields[..] {
+            [.., "missing"] => Ok(None),
+            [_, object_type, size] => {
+                let size = size
+                    .parse::<usize>()
+                    .map_err(|_| unexpected_output(&header))?;
+                let mut contents = vec![0; size + 1];
+                self.stdout.read_exact(&mut contents)?;
+                contents.pop();
+                // eg a directory that has since been replaced by a file
+                Ok((object_type == "blob").then_some(contents))
+            }
+            _ => Err(unexpected_output(&header)),
+        }
+    }
+}
+
+impl Drop for RevisionReader {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+fn unexpected_output(header: &str) -> io::Error {
+    if header.is_empty() {
+        return io::Error::new(io::ErrorKind::UnexpectedEof, "git cat-file exited");
+    }
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected git cat-file output {:?}", header.trim_end()),
+    )
+}
+
+/// Returns the trimmed stdout of a successful git command.
+fn run_git(args: &[&str]) -> Result<String, Error> {
+    let output = Command::new("git")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|source| Error::GitCommandError { source })?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        let message = message.trim();
+        return Err(Error::GitError {
+            message: message
+                .strip_prefix("fatal: ")
+                .unwrap_or(message)
+                .to_owned(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+fn new_parse_errors(
+    source: &[u8],
+    old_source: Option<Vec<u8>>,
+    language: SupportedLanguage,
+) -> Vec<ParseError> {
+    if old_source.as_deref() == Some(source) {
+        return vec![];
+    }
+    let mut parser = get_parser(language.language());
+    let parse_errors = find_parse_errors(&mut parser, source);
+    if parse_errors.is_empty() {
+        return parse_errors;
+    }
+    let old_parse_errors = old_source
+        .map(|old_source| find_parse_errors(&mut parser, &old_source))
+        .unwrap_or_default();
+    let mut old_counts: HashMap<&ParseErrorKey, usize> = Default::default();
+    for old_parse_error in &old_parse_errors {
+        *old_counts.entry(&old_parse_error.key).or_default() += 1;
+    }
+    // When a parse error occurs more often than before, the later
+    // occurrences are the ones considered new.
+    parse_errors
+        .into_iter()
+        .filter(|parse_error| match old_counts.get_mut(&parse_error.key) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .collect()
+}
+
+fn find_parse_errors(parser: &mut Parser, source: &[u8]) -> Vec<ParseError> {
+    let tree = parser.parse(source, None).unwrap();
+    let mut parse_errors = vec![];
+    collect_parse_errors(tree.root_node(), source, &mut parse_errors);
+    parse_errors
+}
+
+fn collect_parse_errors(node: Node, source: &[u8], parse_errors: &mut Vec<ParseError>) {
+    if node.is_error() || node.is_missing() {
+        parse_errors.push(ParseError::new(node, source));
+        return;
+    }
+    if !node.has_error() {
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_parse_errors(child, source, parse_errors);
+    }
+}
+
+fn write_parse_errors<W: Write>(
+    wtr: &mut W,
+    path: &Path,
+    parse_errors: &[ParseError],
+) -> io::Result<()> {
+    for parse_error in parse_errors {
+        writeln!(
+            wtr,
+            "{}:{}:{}: {}",
+            path.display(),
+            parse_error.start_point.row + 1,
+            parse_error.start_point.column + 1,
+            parse_error.key.description,
+        )?;
+    }
+    Ok(())
+}
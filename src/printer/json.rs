@@ -0,0 +1,357 @@
+// derived from https://github.com/BurntSushi/ripgrep/blob/master/crates/printer/src/json.rs
+
+use std::{
+    borrow::Cow,
+    io::{self, Write},
+    path::Path,
+    str,
+    time::Instant,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Serialize, Serializer};
+
+use super::{
+    counter::CounterWriter,
+    stats::Stats,
+    util::{add_search_to_stats, NiceDuration},
+};
+use crate::{
+    language::SupportedLanguage,
+    searcher::Searcher,
+    sink::{MatchedNode, Sink, SinkContext, SinkContextKind, SinkFinish, SinkMatch},
+};
+
+#[derive(Debug, Clone, Default)]
+struct Config {
+    max_matches: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct JsonBuilder {
+    config: Config,
+}
+
+impl JsonBuilder {
+    pub fn new() -> JsonBuilder {
+        JsonBuilder {
+            config: Config::default(),
+        }
+    }
+
+    pub fn build<W: io::Write>(&self, wtr: W) -> Json<W> {
+        Json {
+            config: self.config.clone(),
+            wtr: CounterWriter::new(wtr),
+        }
+    }
+
+    pub fn max_matches(&mut self, limit: Option<u64>) -> &mut JsonBuilder {
+        self.config.max_matches = limit;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct Json<W> {
+    config: Config,
+    wtr: CounterWriter<W>,
+}
+
+impl<W: io::Write> Json<W> {
+    pub fn sink_with_path<'p, 's, P>(
+        &'s mut self,
+        path: &'p P,
+        language: SupportedLanguage,
+    ) -> JsonSink<'p, 's, W>
+    where
+        P: ?Sized + AsRef<Path>,
+    {
+        JsonSink {
+            json: self,
+            path: path.as_ref(),
+            language,
+            start_time: Instant::now(),
+            match_count: 0,
+            after_context_remaining: 0,
+            begin_printed: false,
+            stats: Stats::new(),
+        }
+    }
+
+    pub fn write_summary(&mut self, elapsed_total: NiceDuration, stats: &Stats) -> io::Result<()> {
+        self.write_message(&Message::Summary(Summary {
+            elapsed_total,
+            stats,
+        }))
+    }
+
+    fn write_message(&mut self, message: &Message<'_>) -> io::Result<()> {
+        json_to_writer(&mut self.wtr, message)?;
+        self.wtr.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+impl<W> Json<W> {
+    pub fn get_mut(&mut self) -> &mut W {
+        self.wtr.get_mut()
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonSink<'p, 's, W> {
+    json: &'s mut Json<W>,
+    path: &'p Path,
+    language: SupportedLanguage,
+    start_time: Instant,
+    match_count: u64,
+    after_context_remaining: u64,
+    begin_printed: bool,
+    stats: Stats,
+}
+
+impl<'p, 's, W: io::Write> JsonSink<'p, 's, W> {
+    pub fn has_match(&self) -> bool {
+        self.match_count > 0
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    fn should_quit(&self) -> bool {
+        let limit = match self.json.config.max_matches {
+            None => return false,
+            Some(limit) => limit,
+        };
+        if self.match_count < limit {
+            return false;
+        }
+        self.after_context_remaining == 0
+    }
+
+    fn write_begin_message(&mut self) -> io::Result<()> {
+        if self.begin_printed {
+            return Ok(());
+        }
+        let msg = Message::Begin(Begin {
+            path: self.path,
+            language: self.language,
+        });
+        self.json.write_message(&msg)?;
+        self.begin_printed = true;
+        Ok(())
+    }
+}
+
+impl<'p, 's, W: io::Write> Sink for JsonSink<'p, 's, W> {
+    type Error = io::Error;
+
+    fn matched(&mut self, searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, io::Error> {
+        self.write_begin_message()?;
+
+        self.match_count += 1;
+        self.after_context_remaining = searcher.after_context() as u64;
+        self.stats.add_matches(mat.matched_nodes().len() as u64);
+        self.stats.add_matched_lines(mat.lines().count() as u64);
+
+        for matched_node in mat.matched_nodes() {
+            let msg = Message::Match(NodeMatch {
+                path: self.path,
+                language: self.language,
+                kind: matched_node.kind(),
+                capture: mat.capture_name(matched_node),
+                text: &mat.buffer()[matched_node.range()],
+                start: Position::start_of(searcher, matched_node),
+                end: Position::end_of(searcher, matched_node),
+            });
+            self.json.write_message(&msg)?;
+        }
+        Ok(!self.should_quit())
+    }
+
+    fn context(&mut self, searcher: &Searcher, ctx: &SinkContext<'_>) -> Result<bool, io::Error> {
+        self.write_begin_message()?;
+
+        if ctx.kind() == &SinkContextKind::After {
+            self.after_context_remaining = self.after_context_remaining.saturating_sub(1);
+        }
+        let msg = Message::Context(Context {
+            path: self.path,
+            lines: ctx.bytes(),
+            line_number: ctx.line_number(),
+            absolute_offset: searcher.original_byte_offset(ctx.absolute_byte_offset()),
+        });
+        self.json.write_message(&msg)?;
+        Ok(!self.should_quit())
+    }
+
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
+        self.json.wtr.reset_count();
+        self.start_time = Instant::now();
+        self.match_count = 0;
+        self.after_context_remaining = 0;
+        if self.json.config.max_matches == Some(0) {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn finish(&mut self, _searcher: &Searcher, finish: &SinkFinish) -> Result<(), io::Error> {
+        add_search_to_stats(
+            &mut self.stats,
+            self.start_time.elapsed(),
+            finish,
+            self.match_count > 0,
+            self.json.wtr.count(),
+        );
+
+        if !self.begin_printed {
+            return Ok(());
+        }
+        let msg = Message::End(End {
+            path: self.path,
+            elapsed: NiceDuration(self.start_time.elapsed()),
+            stats: &self.stats,
+        });
+        self.json.write_message(&msg)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+enum Message<'a> {
+    Begin(Begin<'a>),
+    End(End<'a>),
+    Match(NodeMatch<'a>),
+    Context(Context<'a>),
+    Summary(Summary<'a>),
+}
+
+#[derive(Serialize)]
+struct Begin<'a> {
+    #[serde(serialize_with = "ser_path")]
+    path: &'a Path,
+    #[serde(serialize_with = "ser_display")]
+    language: SupportedLanguage,
+}
+
+#[derive(Serialize)]
+struct End<'a> {
+    #[serde(serialize_with = "ser_path")]
+    path: &'a Path,
+    elapsed: NiceDuration,
+    stats: &'a Stats,
+}
+
+#[derive(Serialize)]
+struct NodeMatch<'a> {
+    #[serde(serialize_with = "ser_path")]
+    path: &'a Path,
+    #[serde(serialize_with = "ser_display")]
+    language: SupportedLanguage,
+    kind: &'a str,
+    capture: &'a str,
+    #[serde(serialize_with = "ser_bytes")]
+    text: &'a [u8],
+    start: Position,
+    end: Position,
+}
+
+#[derive(Serialize)]
+struct Context<'a> {
+    #[serde(serialize_with = "ser_path")]
+    path: &'a Path,
+    #[serde(serialize_with = "ser_bytes")]
+    lines: &'a [u8],
+    line_number: Option<u64>,
+    absolute_offset: u64,
+}
+
+#[derive(Serialize)]
+struct Summary<'a> {
+    elapsed_total: NiceDuration,
+    stats: &'a Stats,
+}
+
+#[derive(Serialize)]
+struct Position {
+    byte: usize,
+    row: usize,
+    column: usize,
+}
+
+impl Position {
+    fn start_of(searcher: &Searcher, matched_node: &MatchedNode) -> Self {
+        Self {
+            byte: searcher.original_byte_offset(matched_node.range().start() as u64) as usize,
+            row: matched_node.start_point().row,
+            column: matched_node.start_point().column,
+        }
+    }
+
+    fn end_of(searcher: &Searcher, matched_node: &MatchedNode) -> Self {
+        Self {
+            byte: searcher.original_byte_offset(matched_node.range().end() as u64) as usize,
+            row: matched_node.end_point().row,
+            column: matched_node.end_point().column,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Data<'a> {
+    Text { text: Cow<'a, str> },
+    Bytes { bytes: String },
+}
+
+impl<'a> Data<'a> {
+    fn from_bytes(bytes: &'a [u8]) -> Data<'a> {
+        match str::from_utf8(bytes) {
+            Ok(text) => Data::Text {
+                text: Cow::Borrowed(text),
+            },
+            Err(_) => Data::Bytes {
+                bytes: BASE64.encode(bytes),
+            },
+        }
+    }
+
+    #[cfg(unix)]
+    fn from_path(path: &Path) -> Data<'_> {
+        use std::os::unix::ffi::OsStrExt;
+
+        Data::from_bytes(path.as_os_str().as_bytes())
+    }
+
+    #[cfg(not(unix))]
+    fn from_path(path: &Path) -> Data<'_> {
+        match path.to_str() {
+            Some(text) => Data::Text {
+                text: Cow::Borrowed(text),
+            },
+            None => Data::Text {
+                text: path.to_string_lossy(),
+            },
+        }
+    }
+}
+
+fn ser_bytes<S: Serializer>(bytes: &&[u8], ser: S) -> Result<S::Ok, S::Error> {
+    Data::from_bytes(bytes).serialize(ser)
+}
+
+fn ser_path<S: Serializer>(path: &&Path, ser: S) -> Result<S::Ok, S::Error> {
+    Data::from_path(path).serialize(ser)
+}
+
+fn ser_display<S: Serializer>(value: &impl std::fmt::Display, ser: S) -> Result<S::Ok, S::Error> {
+    ser.collect_str(value)
+}
+
+fn json_to_writer<W: io::Write>(wtr: W, message: &Message<'_>) -> io::Result<()> {
+    serde_json::to_writer(wtr, message).map_err(io::Error::from)
+}
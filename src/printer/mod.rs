@@ -1,7 +1,24 @@
 mod color;
 mod counter;
+mod json;
+mod node_text;
+mod review;
+mod rewrite;
+mod sarif;
 mod standard;
 mod stats;
+mod summary;
+mod template;
 mod util;
 
+pub use color::{default_color_specs, ColorSpecs, UserColorSpec};
+pub use json::{Json, JsonBuilder};
+pub use node_text::{NodeText, NodeTextBuilder};
+pub use review::Review;
+pub use rewrite::{PendingRewrite, Rewrite, RewriteBuilder, RewriteFailure, RewriteMode};
+pub use sarif::{Sarif, SarifBuilder, SarifResult};
 pub use standard::{Standard, StandardBuilder};
+pub use stats::Stats;
+pub use summary::{Summary, SummaryBuilder, SummaryKind};
+pub use template::{FormatTemplate, Template, TemplateBuilder};
+pub use util::{NiceDuration, ReplaceTemplate};
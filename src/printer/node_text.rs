@@ -0,0 +1,206 @@
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    path::Path,
+    time::Instant,
+};
+
+use termcolor::WriteColor;
+
+use super::{
+    counter::CounterWriter,
+    stats::Stats,
+    util::{add_search_to_stats, PrinterPath},
+};
+use crate::{
+    searcher::Searcher,
+    sink::{MatchedNode, Sink, SinkFinish, SinkMatch},
+};
+
+#[derive(Debug, Clone)]
+struct Config {
+    terminator: u8,
+    header: bool,
+    separator: Option<Vec<u8>>,
+    stats: bool,
+    max_matches: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            terminator: b'\n',
+            header: false,
+            separator: None,
+            stats: false,
+            max_matches: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NodeTextBuilder {
+    config: Config,
+}
+
+impl NodeTextBuilder {
+    pub fn new() -> NodeTextBuilder {
+        NodeTextBuilder {
+            config: Config::default(),
+        }
+    }
+
+    pub fn build<W: WriteColor>(&self, wtr: W) -> NodeText<W> {
+        NodeText {
+            config: self.config.clone(),
+            wtr: RefCell::new(CounterWriter::new(wtr)),
+        }
+    }
+
+    pub fn terminator(&mut self, terminator: u8) -> &mut NodeTextBuilder {
+        self.config.terminator = terminator;
+        self
+    }
+
+    /// Precede the text of each node with a `path:line:column:` line.
+    pub fn header(&mut self, yes: bool) -> &mut NodeTextBuilder {
+        self.config.header = yes;
+        self
+    }
+
+    /// Print `sep` (followed by the terminator) between the nodes of a file.
+    pub fn separator(&mut self, sep: Option<Vec<u8>>) -> &mut NodeTextBuilder {
+        self.config.separator = sep;
+        self
+    }
+
+    pub fn stats(&mut self, yes: bool) -> &mut NodeTextBuilder {
+        self.config.stats = yes;
+        self
+    }
+
+    pub fn max_matches(&mut self, limit: Option<u64>) -> &mut NodeTextBuilder {
+        self.config.max_matches = limit;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct NodeText<W> {
+    config: Config,
+    wtr: RefCell<CounterWriter<W>>,
+}
+
+impl<W: WriteColor> NodeText<W> {
+    pub fn sink_with_path<'p, 's, P>(&'s mut self, path: &'p P) -> NodeTextSink<'p, 's, W>
+    where
+        P: ?Sized + AsRef<Path>,
+    {
+        let stats = if self.config.stats {
+            Some(Stats::new())
+        } else {
+            None
+        };
+        NodeTextSink {
+            node_text: self,
+            path: PrinterPath::new(path.as_ref()),
+            start_time: Instant::now(),
+            match_count: 0,
+            stats,
+        }
+    }
+}
+
+impl<W> NodeText<W> {
+    pub fn get_mut(&mut self) -> &mut W {
+        self.wtr.get_mut().get_mut()
+    }
+}
+
+#[derive(Debug)]
+pub struct NodeTextSink<'p, 's, W> {
+    node_text: &'s mut NodeText<W>,
+    path: PrinterPath<'p>,
+    start_time: Instant,
+    match_count: u64,
+    stats: Option<Stats>,
+}
+
+impl<'p, 's, W: WriteColor> NodeTextSink<'p, 's, W> {
+    pub fn has_match(&self) -> bool {
+        self.match_count > 0
+    }
+
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+
+    fn should_quit(&self) -> bool {
+        match self.node_text.config.max_matches {
+            None => false,
+            Some(limit) => self.match_count >= limit,
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> io::Result<()> {
+        self.node_text.wtr.borrow_mut().write_all(buf)
+    }
+
+    fn write_header(&self, matched_node: &MatchedNode) -> io::Result<()> {
+        let start_point = matched_node.start_point();
+        self.write(self.path.as_bytes())?;
+        self.write(format!(":{}:{}:", start_point.row + 1, start_point.column + 1).as_bytes())?;
+        self.write(&[self.node_text.config.terminator])
+    }
+}
+
+impl<'p, 's, W: WriteColor> Sink for NodeTextSink<'p, 's, W> {
+    type Error = io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, io::Error> {
+        let is_first_match = self.match_count == 0;
+        self.match_count += 1;
+        if let Some(ref mut stats) = self.stats {
+            stats.add_matches(mat.matched_nodes().len() as u64);
+            stats.add_matched_lines(mat.lines().count() as u64);
+        }
+
+        for (index, matched_node) in mat.matched_nodes().iter().enumerate() {
+            if let Some(separator) = self.node_text.config.separator.as_ref() {
+                if !is_first_match || index > 0 {
+                    self.write(separator)?;
+                    self.write(&[self.node_text.config.terminator])?;
+                }
+            }
+            if self.node_text.config.header {
+                self.write_header(matched_node)?;
+            }
+            self.write(&mat.buffer()[matched_node.range()])?;
+            self.write(&[self.node_text.config.terminator])?;
+        }
+        Ok(!self.should_quit())
+    }
+
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
+        self.node_text.wtr.borrow_mut().reset_count();
+        self.start_time = Instant::now();
+        self.match_count = 0;
+        if self.node_text.config.max_matches == Some(0) {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn finish(&mut self, _searcher: &Searcher, finish: &SinkFinish) -> Result<(), io::Error> {
+        if let Some(stats) = self.stats.as_mut() {
+            add_search_to_stats(
+                stats,
+                self.start_time.elapsed(),
+                finish,
+                self.match_count > 0,
+                self.node_text.wtr.borrow().count(),
+            );
+        }
+        Ok(())
+    }
+}
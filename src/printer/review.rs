@@ -0,0 +1,136 @@
+use std::io::{self, BufRead, Write};
+
+use bstr::ByteSlice;
+
+use super::rewrite::{write_diff_header, write_hunks, PendingRewrite};
+
+const REVIEW_HELP: &[u8] = b"\
+y - rewrite this match
+n - don't rewrite this match
+e - manually edit the replacement for this match
+a - rewrite this match and all the remaining ones in this file
+q - quit; don't rewrite this match or any of the remaining ones
+";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Answer {
+    Yes,
+    No,
+    Edit,
+    All,
+    Quit,
+}
+
+/// Asks whether to apply each edit of a [`PendingRewrite`], one at a time.
+///
+/// Reviewing isn't done from the parallel search, so that the edits of
+/// different files never get interleaved on the terminal.
+#[derive(Debug)]
+pub struct Review<R, W> {
+    input: R,
+    wtr: W,
+    quit: bool,
+}
+
+impl<R: BufRead, W: Write> Review<R, W> {
+    pub fn new(input: R, wtr: W) -> Review<R, W> {
+        Review {
+            input,
+            wtr,
+            quit: false,
+        }
+    }
+
+    /// Whether "q" was answered (or the input ended), after which nothing
+    /// else should be reviewed.
+    pub fn has_quit(&self) -> bool {
+        self.quit
+    }
+
+    /// Drops the edits of `pending_rewrite` that didn't get accepted, leaving
+    /// it ready to be applied.
+    pub fn review(&mut self, pending_rewrite: &mut PendingRewrite) -> io::Result<()> {
+        let edits = std::mem::take(&mut pending_rewrite.edits);
+        if self.quit {
+            return Ok(());
+        }
+        write_diff_header(&mut self.wtr, &pending_rewrite.path)?;
+        let edit_count = edits.len();
+        let mut accept_remaining = false;
+        for (index, mut edit) in edits.into_iter().enumerate() {
+            if self.quit {
+                break;
+            }
+            if accept_remaining {
+                pending_rewrite.edits.push(edit);
+                continue;
+            }
+            write_hunks(
+                &mut self.wtr,
+                &pending_rewrite.original,
+                std::slice::from_ref(&edit),
+            )?;
+            match self.ask(index + 1, edit_count)? {
+                Answer::Yes => pending_rewrite.edits.push(edit),
+                Answer::No => (),
+                Answer::Edit => {
+                    self.write_prompt(b"Replacement: ")?;
+                    if let Some(replacement) = self.read_line()? {
+                        edit.replacement = replacement;
+                        pending_rewrite.edits.push(edit);
+                    }
+                }
+                Answer::All => {
+                    accept_remaining = true;
+                    pending_rewrite.edits.push(edit);
+                }
+                Answer::Quit => self.quit = true,
+            }
+        }
+        Ok(())
+    }
+
+    fn ask(&mut self, number: usize, count: usize) -> io::Result<Answer> {
+        loop {
+            write!(
+                self.wtr,
+                "({number}/{count}) Rewrite this match [y,n,e,a,q,?]? "
+            )?;
+            self.wtr.flush()?;
+            let line = match self.read_line()? {
+                Some(line) => line,
+                None => return Ok(Answer::Quit),
+            };
+            match line.trim() {
+                b"y" => return Ok(Answer::Yes),
+                b"n" => return Ok(Answer::No),
+                b"e" => return Ok(Answer::Edit),
+                b"a" => return Ok(Answer::All),
+                b"q" => return Ok(Answer::Quit),
+                _ => self.wtr.write_all(REVIEW_HELP)?,
+            }
+        }
+    }
+
+    fn write_prompt(&mut self, prompt: &[u8]) -> io::Result<()> {
+        self.wtr.write_all(prompt)?;
+        self.wtr.flush()
+    }
+
+    /// Reads a line without its line terminator, or `None` (also marking the
+    /// review as quit) once the input has ended.
+    fn read_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut line = vec![];
+        if self.input.read_until(b'\n', &mut line)? == 0 {
+            self.quit = true;
+            return Ok(None);
+        }
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+}
@@ -0,0 +1,502 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use tree_sitter::Point;
+
+use super::{
+    counter::CounterWriter,
+    util::{PrinterPath, ReplaceTemplate},
+};
+use crate::{
+    matcher::Match,
+    searcher::Searcher,
+    sink::{Sink, SinkFinish, SinkMatch},
+};
+
+const DIFF_CONTEXT_LINES: usize = 3;
+
+const UTF_8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RewriteMode {
+    Write,
+    Diff,
+    /// Keep each file's edits as a [`PendingRewrite`] to be reviewed once
+    /// every file has been searched.
+    Interactive,
+}
+
+#[derive(Debug, Clone)]
+struct Config {
+    template: ReplaceTemplate,
+    mode: RewriteMode,
+    max_matches: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RewriteBuilder {
+    config: Config,
+}
+
+impl RewriteBuilder {
+    pub fn new(template: ReplaceTemplate, mode: RewriteMode) -> RewriteBuilder {
+        RewriteBuilder {
+            config: Config {
+                template,
+                mode,
+                max_matches: None,
+            },
+        }
+    }
+
+    pub fn build<W: io::Write>(&self, wtr: W) -> Rewrite<W> {
+        Rewrite {
+            config: self.config.clone(),
+            wtr: CounterWriter::new(wtr),
+        }
+    }
+
+    pub fn max_matches(&mut self, limit: Option<u64>) -> &mut RewriteBuilder {
+        self.config.max_matches = limit;
+        self
+    }
+}
+
+/// Rewrites every matching node of a file with the interpolated template,
+/// either in place or by printing a unified diff.
+///
+/// Nothing is rewritten for a file if any of its matching nodes overlap, since
+/// there's no single correct way to apply both replacements.
+#[derive(Debug)]
+pub struct Rewrite<W> {
+    config: Config,
+    wtr: CounterWriter<W>,
+}
+
+impl<W: io::Write> Rewrite<W> {
+    pub fn sink_with_path<'p, 's, P>(&'s mut self, path: &'p P) -> RewriteSink<'p, 's, W>
+    where
+        P: ?Sized + AsRef<Path>,
+    {
+        RewriteSink {
+            rewrite: self,
+            path: path.as_ref(),
+            match_count: 0,
+            original: vec![],
+            edits: vec![],
+            failure: None,
+        }
+    }
+}
+
+impl<W> Rewrite<W> {
+    pub fn get_mut(&mut self) -> &mut W {
+        self.wtr.get_mut()
+    }
+}
+
+#[derive(Debug)]
+pub enum RewriteFailure {
+    Overlap {
+        first: Point,
+        second: Point,
+    },
+    /// The file was transcoded to UTF-8 to be searched, so writing the
+    /// rewritten text would change its encoding.
+    Transcoded,
+    /// The file was changed by something else while its
+    /// [`PendingRewrite`] was being reviewed.
+    Modified,
+    Write(io::Error),
+}
+
+#[derive(Debug)]
+pub(super) struct Edit {
+    pub(super) range: Match,
+    pub(super) start_point: Point,
+    pub(super) replacement: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct RewriteSink<'p, 's, W> {
+    rewrite: &'s mut Rewrite<W>,
+    path: &'p Path,
+    match_count: u64,
+    original: Vec<u8>,
+    edits: Vec<Edit>,
+    failure: Option<RewriteFailure>,
+}
+
+impl<'p, 's, W: io::Write> RewriteSink<'p, 's, W> {
+    pub fn has_match(&self) -> bool {
+        self.match_count > 0
+    }
+
+    pub fn failure(&self) -> Option<&RewriteFailure> {
+        self.failure.as_ref()
+    }
+
+    pub fn into_pending_rewrite(self) -> Option<PendingRewrite> {
+        if self.rewrite.config.mode != RewriteMode::Interactive
+            || self.failure.is_some()
+            || self.edits.is_empty()
+        {
+            return None;
+        }
+        Some(PendingRewrite {
+            path: self.path.to_owned(),
+            original: self.original,
+            edits: self.edits,
+        })
+    }
+
+    fn should_quit(&self) -> bool {
+        match self.rewrite.config.max_matches {
+            None => false,
+            Some(limit) => self.match_count >= limit,
+        }
+    }
+
+    fn find_overlap(&self) -> Option<RewriteFailure> {
+        self.edits
+            .windows(2)
+            .find(|pair| pair[1].range.start() < pair[0].range.end())
+            .map(|pair| RewriteFailure::Overlap {
+                first: pair[0].start_point,
+                second: pair[1].start_point,
+            })
+    }
+
+    fn write_diff(&mut self) -> io::Result<()> {
+        write_diff_header(&mut self.rewrite.wtr, self.path)?;
+        write_hunks(&mut self.rewrite.wtr, &self.original, &self.edits)
+    }
+}
+
+impl<'p, 's, W: io::Write> Sink for RewriteSink<'p, 's, W> {
+    type Error = io::Error;
+
+    fn matched(&mut self, searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, io::Error> {
+        if searcher.is_transcoded() {
+            self.failure = Some(RewriteFailure::Transcoded);
+            return Ok(false);
+        }
+        self.match_count += 1;
+        if self.original.is_empty() {
+            // the searched text only differs from the file by a stripped
+            // UTF-8 BOM, which has to be kept
+            if searcher.original_byte_offset(0) > 0 {
+                self.original.extend_from_slice(UTF_8_BOM);
+            }
+            self.original.extend_from_slice(mat.buffer());
+        }
+        for matched_node in mat.matched_nodes() {
+            let mut replacement = vec![];
+            self.rewrite
+                .config
+                .template
+                .interpolate(mat, matched_node, &mut replacement);
+            let range = matched_node.range();
+            self.edits.push(Edit {
+                range: Match::new(
+                    searcher.original_byte_offset(range.start() as u64) as usize,
+                    searcher.original_byte_offset(range.end() as u64) as usize,
+                ),
+                start_point: matched_node.start_point(),
+                replacement,
+            });
+        }
+        Ok(!self.should_quit())
+    }
+
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
+        self.rewrite.wtr.reset_count();
+        self.match_count = 0;
+        self.original.clear();
+        self.edits.clear();
+        self.failure = None;
+        if self.rewrite.config.max_matches == Some(0) {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn finish(&mut self, _searcher: &Searcher, _finish: &SinkFinish) -> Result<(), io::Error> {
+        if self.failure.is_some() {
+            return Ok(());
+        }
+        self.edits.sort_by_key(|edit| edit.range.start());
+        if let Some(overlap) = self.find_overlap() {
+            self.failure = Some(overlap);
+            return Ok(());
+        }
+        let original = &self.original;
+        self.edits
+            .retain(|edit| original[edit.range] != edit.replacement[..]);
+        if self.edits.is_empty() {
+            return Ok(());
+        }
+        match self.rewrite.config.mode {
+            RewriteMode::Write => {
+                if let Err(error) =
+                    write_atomically(self.path, &rewritten(&self.original, &self.edits))
+                {
+                    self.failure = Some(RewriteFailure::Write(error));
+                }
+                Ok(())
+            }
+            RewriteMode::Diff => self.write_diff(),
+            RewriteMode::Interactive => Ok(()),
+        }
+    }
+}
+
+/// The edits of a file found by [`RewriteMode::Interactive`], which only get
+/// applied once they've been reviewed.
+#[derive(Debug)]
+pub struct PendingRewrite {
+    pub(super) path: PathBuf,
+    pub(super) original: Vec<u8>,
+    pub(super) edits: Vec<Edit>,
+}
+
+impl PendingRewrite {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes the accepted edits, unless the file no longer has the contents
+    /// they were made against.
+    pub fn apply(&self) -> Result<(), RewriteFailure> {
+        if self.edits.is_empty() {
+            return Ok(());
+        }
+        let current = fs::read(&self.path).map_err(RewriteFailure::Write)?;
+        if current != self.original {
+            return Err(RewriteFailure::Modified);
+        }
+        write_atomically(&self.path, &rewritten(&self.original, &self.edits))
+            .map_err(RewriteFailure::Write)
+    }
+}
+
+fn rewritten(original: &[u8], edits: &[Edit]) -> Vec<u8> {
+    let mut rewritten = Vec::with_capacity(original.len());
+    let mut last_end = 0;
+    for edit in edits {
+        rewritten.extend_from_slice(&original[last_end..edit.range.start()]);
+        rewritten.extend_from_slice(&edit.replacement);
+        last_end = edit.range.end();
+    }
+    rewritten.extend_from_slice(&original[last_end..]);
+    rewritten
+}
+
+/// Writes to a temporary sibling of `path` which then gets renamed over it, so
+/// that `path` never ends up partially written.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file"))?;
+    let temp_path = path.with_file_name(format!(
+        ".{}.tree-sitter-grep-rewrite",
+        file_name.to_string_lossy()
+    ));
+    let permissions = fs::metadata(path)?.permissions();
+    let result = write_and_rename(&temp_path, contents, permissions, path);
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+fn write_and_rename(
+    temp_path: &Path,
+    contents: &[u8],
+    permissions: fs::Permissions,
+    path: &Path,
+) -> io::Result<()> {
+    let mut file = File::create(temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::set_permissions(temp_path, permissions)?;
+    fs::rename(temp_path, path)
+}
+
+pub(super) fn write_diff_header<W: io::Write>(wtr: &mut W, path: &Path) -> io::Result<()> {
+    let path = PrinterPath::new(path);
+    wtr.write_all(b"--- a/")?;
+    wtr.write_all(path.as_bytes())?;
+    wtr.write_all(b"\n+++ b/")?;
+    wtr.write_all(path.as_bytes())?;
+    wtr.write_all(b"\n")
+}
+
+/// Writes the unified diff hunks for `edits`, which must be sorted and
+/// non-overlapping.
+pub(super) fn write_hunks<W: io::Write>(
+    wtr: &mut W,
+    original: &[u8],
+    edits: &[Edit],
+) -> io::Result<()> {
+    let lines = LineIndex::new(original);
+    let blocks = ChangedBlock::from_edits(original, &lines, edits);
+    let mut line_delta: isize = 0;
+    for hunk in hunks(&blocks, 2 * DIFF_CONTEXT_LINES) {
+        let first_line = hunk[0].first_line.saturating_sub(DIFF_CONTEXT_LINES);
+        let last_line = (hunk[hunk.len() - 1].last_line + DIFF_CONTEXT_LINES).min(lines.len() - 1);
+        let old_count = last_line - first_line + 1;
+        let new_count = hunk.iter().fold(old_count, |count, block| {
+            count - block.old_line_count() + block.new_lines.len()
+        });
+        let new_first_line = (first_line as isize + line_delta) as usize;
+        writeln!(
+            wtr,
+            "@@ -{},{} +{},{} @@",
+            first_line + 1,
+            old_count,
+            if new_count == 0 {
+                new_first_line
+            } else {
+                new_first_line + 1
+            },
+            new_count,
+        )?;
+        let mut line = first_line;
+        for block in hunk {
+            while line < block.first_line {
+                write_diff_line(wtr, b' ', lines.line(original, line))?;
+                line += 1;
+            }
+            for old_line in block.first_line..=block.last_line {
+                write_diff_line(wtr, b'-', lines.line(original, old_line))?;
+            }
+            for new_line in &block.new_lines {
+                write_diff_line(wtr, b'+', new_line)?;
+            }
+            line = block.last_line + 1;
+        }
+        while line <= last_line {
+            write_diff_line(wtr, b' ', lines.line(original, line))?;
+            line += 1;
+        }
+        line_delta += new_count as isize - old_count as isize;
+    }
+    Ok(())
+}
+
+fn write_diff_line<W: io::Write>(wtr: &mut W, prefix: u8, line: &[u8]) -> io::Result<()> {
+    wtr.write_all(&[prefix])?;
+    wtr.write_all(line)?;
+    if !line.ends_with(b"\n") {
+        wtr.write_all(b"\n\\ No newline at end of file\n")?;
+    }
+    Ok(())
+}
+
+struct LineIndex {
+    starts: Vec<usize>,
+    len: usize,
+}
+
+impl LineIndex {
+    fn new(bytes: &[u8]) -> Self {
+        let mut starts = vec![0];
+        starts.extend(
+            bytes
+                .iter()
+                .enumerate()
+                .filter(|&(i, &b)| b == b'\n' && i + 1 < bytes.len())
+                .map(|(i, _)| i + 1),
+        );
+        Self {
+            starts,
+            len: bytes.len(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    fn start(&self, line: usize) -> usize {
+        self.starts[line]
+    }
+
+    fn end(&self, line: usize) -> usize {
+        self.starts.get(line + 1).copied().unwrap_or(self.len)
+    }
+
+    fn line<'b>(&self, bytes: &'b [u8], line: usize) -> &'b [u8] {
+        &bytes[self.start(line)..self.end(line)]
+    }
+}
+
+/// A run of whole lines touched by one or more edits.
+struct ChangedBlock {
+    first_line: usize,
+    last_line: usize,
+    new_lines: Vec<Vec<u8>>,
+}
+
+impl ChangedBlock {
+    fn from_edits(original: &[u8], lines: &LineIndex, edits: &[Edit]) -> Vec<ChangedBlock> {
+        let mut blocks: Vec<(usize, usize, Vec<&Edit>)> = vec![];
+        for edit in edits {
+            let first_line = lines.line_of(edit.range.start());
+            let last_line = lines.line_of(edit.range.end().max(edit.range.start() + 1) - 1);
+            match blocks.last_mut() {
+                Some((_, block_last_line, block_edits)) if first_line <= *block_last_line => {
+                    *block_last_line = (*block_last_line).max(last_line);
+                    block_edits.push(edit);
+                }
+                _ => blocks.push((first_line, last_line, vec![edit])),
+            }
+        }
+        blocks
+            .into_iter()
+            .map(|(first_line, last_line, block_edits)| {
+                let mut new_text = vec![];
+                let mut last_end = lines.start(first_line);
+                for edit in block_edits {
+                    new_text.extend_from_slice(&original[last_end..edit.range.start()]);
+                    new_text.extend_from_slice(&edit.replacement);
+                    last_end = edit.range.end();
+                }
+                new_text.extend_from_slice(&original[last_end..lines.end(last_line)]);
+                ChangedBlock {
+                    first_line,
+                    last_line,
+                    new_lines: new_text
+                        .split_inclusive(|&b| b == b'\n')
+                        .map(<[u8]>::to_vec)
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+
+    fn old_line_count(&self) -> usize {
+        self.last_line - self.first_line + 1
+    }
+}
+
+/// Groups blocks separated by at most `max_gap` unchanged lines into hunks.
+fn hunks(blocks: &[ChangedBlock], max_gap: usize) -> Vec<&[ChangedBlock]> {
+    let mut hunks = vec![];
+    let mut hunk_start = 0;
+    for i in 1..=blocks.len() {
+        if i == blocks.len() || blocks[i].first_line - blocks[i - 1].last_line - 1 > max_gap {
+            hunks.push(&blocks[hunk_start..i]);
+            hunk_start = i;
+        }
+    }
+    hunks
+}
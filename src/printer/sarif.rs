@@ -0,0 +1,331 @@
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+    path::Path,
+};
+
+use bstr::ByteSlice;
+use serde::Serialize;
+
+use super::counter::CounterWriter;
+use crate::{
+    searcher::Searcher,
+    sink::{MatchedNode, Sink, SinkMatch},
+};
+
+const SARIF_VERSION: &str = "2.1.0";
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+#[derive(Debug, Clone, Default)]
+struct Config {
+    rule_id: Option<String>,
+    message: Option<String>,
+    max_matches: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SarifBuilder {
+    config: Config,
+}
+
+impl SarifBuilder {
+    pub fn new() -> SarifBuilder {
+        SarifBuilder {
+            config: Config::default(),
+        }
+    }
+
+    pub fn build<W: io::Write>(&self, wtr: W) -> Sarif<W> {
+        Sarif {
+            config: self.config.clone(),
+            wtr: CounterWriter::new(wtr),
+        }
+    }
+
+    /// When not set, each result's rule id is the name of the capture that
+    /// matched.
+    pub fn rule_id(&mut self, rule_id: Option<String>) -> &mut SarifBuilder {
+        self.config.rule_id = rule_id;
+        self
+    }
+
+    /// When not set, each result's message names the kind of the matching
+    /// node.
+    pub fn message(&mut self, message: Option<String>) -> &mut SarifBuilder {
+        self.config.message = message;
+        self
+    }
+
+    pub fn max_matches(&mut self, limit: Option<u64>) -> &mut SarifBuilder {
+        self.config.max_matches = limit;
+        self
+    }
+}
+
+/// Unlike the other printers, nothing gets written while searching. Instead
+/// each sink collects its results, and once every file has been searched
+/// they're all written as a single SARIF log by [`Sarif::write_log`].
+#[derive(Debug)]
+pub struct Sarif<W> {
+    config: Config,
+    wtr: CounterWriter<W>,
+}
+
+impl<W: io::Write> Sarif<W> {
+    pub fn sink_with_path<'p, 's, P>(&'s mut self, path: &'p P) -> SarifSink<'p, 's, W>
+    where
+        P: ?Sized + AsRef<Path>,
+    {
+        SarifSink {
+            sarif: self,
+            path: path.as_ref(),
+            match_count: 0,
+            results: Default::default(),
+        }
+    }
+
+    pub fn write_log(&mut self, mut results: Vec<SarifResult>) -> io::Result<()> {
+        results.sort_by(|a, b| (&a.uri, a.region.byte_offset).cmp(&(&b.uri, b.region.byte_offset)));
+        let rule_ids = results
+            .iter()
+            .map(|result| &*result.rule_id)
+            .collect::<BTreeSet<_>>();
+        let log = Log {
+            version: SARIF_VERSION,
+            schema: SARIF_SCHEMA,
+            runs: [Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: env!("CARGO_PKG_NAME"),
+                        version: env!("CARGO_PKG_VERSION"),
+                        information_uri: env!("CARGO_PKG_HOMEPAGE"),
+                        rules: rule_ids.into_iter().map(|id| Rule { id }).collect(),
+                    },
+                },
+                column_kind: "unicodeCodePoints",
+                results: results.iter().map(ResultMessage::from).collect(),
+            }],
+        };
+        serde_json::to_writer_pretty(&mut self.wtr, &log).map_err(io::Error::from)?;
+        self.wtr.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+impl<W> Sarif<W> {
+    pub fn get_mut(&mut self) -> &mut W {
+        self.wtr.get_mut()
+    }
+}
+
+#[derive(Debug)]
+pub struct SarifSink<'p, 's, W> {
+    sarif: &'s mut Sarif<W>,
+    path: &'p Path,
+    match_count: u64,
+    results: Vec<SarifResult>,
+}
+
+impl<'p, 's, W: io::Write> SarifSink<'p, 's, W> {
+    pub fn has_match(&self) -> bool {
+        self.match_count > 0
+    }
+
+    pub fn into_results(self) -> Vec<SarifResult> {
+        self.results
+    }
+
+    fn should_quit(&self) -> bool {
+        match self.sarif.config.max_matches {
+            None => false,
+            Some(limit) => self.match_count >= limit,
+        }
+    }
+
+    fn result_for(
+        &self,
+        searcher: &Searcher,
+        mat: &SinkMatch<'_>,
+        matched_node: &MatchedNode,
+    ) -> SarifResult {
+        let config = &self.sarif.config;
+        SarifResult {
+            rule_id: config
+                .rule_id
+                .clone()
+                .unwrap_or_else(|| mat.capture_name(matched_node).to_owned()),
+            message: config
+                .message
+                .clone()
+                .unwrap_or_else(|| format!("Matched {} node", matched_node.kind())),
+            uri: path_to_uri(self.path),
+            region: Region::new(searcher, mat.buffer(), matched_node),
+        }
+    }
+}
+
+impl<'p, 's, W: io::Write> Sink for SarifSink<'p, 's, W> {
+    type Error = io::Error;
+
+    fn matched(&mut self, searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, io::Error> {
+        self.match_count += 1;
+        for matched_node in mat.matched_nodes() {
+            let result = self.result_for(searcher, mat, matched_node);
+            self.results.push(result);
+        }
+        Ok(!self.should_quit())
+    }
+
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
+        self.match_count = 0;
+        self.results.clear();
+        if self.sarif.config.max_matches == Some(0) {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SarifResult {
+    rule_id: String,
+    message: String,
+    uri: String,
+    region: Region,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+    byte_offset: usize,
+    byte_length: usize,
+}
+
+impl Region {
+    // SARIF lines and columns are 1-based, and columns are counted in
+    // (Unicode code point) characters rather than tree-sitter's bytes.
+    fn new(searcher: &Searcher, buffer: &[u8], matched_node: &MatchedNode) -> Self {
+        let range = matched_node.range();
+        let byte_offset = searcher.original_byte_offset(range.start() as u64) as usize;
+        let byte_end = searcher.original_byte_offset(range.end() as u64) as usize;
+        Self {
+            start_line: matched_node.start_point().row + 1,
+            start_column: column_in_chars(buffer, range.start()) + 1,
+            end_line: matched_node.end_point().row + 1,
+            end_column: column_in_chars(buffer, range.end()) + 1,
+            byte_offset,
+            byte_length: byte_end - byte_offset,
+        }
+    }
+}
+
+fn column_in_chars(buffer: &[u8], offset: usize) -> usize {
+    let line_start = buffer[..offset]
+        .rfind_byte(b'\n')
+        .map_or(0, |newline| newline + 1);
+    buffer[line_start..offset].chars().count()
+}
+
+// percent-encodes everything but RFC 3986 unreserved characters and path
+// separators
+fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let mut uri = String::with_capacity(path.len());
+    for &byte in path.as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            b'\\' if cfg!(windows) => uri.push('/'),
+            _ => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
+
+#[derive(Serialize)]
+struct Log<'a> {
+    version: &'static str,
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    runs: [Run<'a>; 1],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run<'a> {
+    tool: Tool<'a>,
+    column_kind: &'static str,
+    results: Vec<ResultMessage<'a>>,
+}
+
+#[derive(Serialize)]
+struct Tool<'a> {
+    driver: Driver<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver<'a> {
+    name: &'static str,
+    version: &'static str,
+    information_uri: &'static str,
+    rules: Vec<Rule<'a>>,
+}
+
+#[derive(Serialize)]
+struct Rule<'a> {
+    id: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResultMessage<'a> {
+    rule_id: &'a str,
+    message: Text<'a>,
+    locations: [Location<'a>; 1],
+}
+
+impl<'a> From<&'a SarifResult> for ResultMessage<'a> {
+    fn from(result: &'a SarifResult) -> Self {
+        Self {
+            rule_id: &result.rule_id,
+            message: Text {
+                text: &result.message,
+            },
+            locations: [Location {
+                physical_location: PhysicalLocation {
+                    artifact_location: ArtifactLocation { uri: &result.uri },
+                    region: &result.region,
+                },
+            }],
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Text<'a> {
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location<'a> {
+    physical_location: PhysicalLocation<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation<'a> {
+    artifact_location: ArtifactLocation<'a>,
+    region: &'a Region,
+}
+
+#[derive(Serialize)]
+struct ArtifactLocation<'a> {
+    uri: &'a str,
+}
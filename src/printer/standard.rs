@@ -16,13 +16,16 @@ use super::{
     color::ColorSpecs,
     counter::CounterWriter,
     stats::Stats,
-    util::{trim_ascii_prefix, trim_line_terminator, PrinterPath, Sunk},
+    util::{
+        add_search_to_stats, replaced_match_indices, trim_ascii_prefix, trim_line_terminator,
+        PrinterPath, ReplaceTemplate, Replacer, Sunk,
+    },
 };
 use crate::{
     lines::LineStep,
     matcher::Match,
     searcher::Searcher,
-    sink::{Sink, SinkContext, SinkContextKind, SinkFinish, SinkMatch},
+    sink::{MatchedNode, Sink, SinkContext, SinkContextKind, SinkFinish, SinkMatch},
 };
 
 #[derive(Debug, Clone)]
@@ -37,7 +40,9 @@ struct Config {
     max_columns: Option<u64>,
     max_columns_preview: bool,
     max_matches: Option<u64>,
+    replacement: Arc<Option<ReplaceTemplate>>,
     column: bool,
+    capture_labels: bool,
     byte_offset: bool,
     trim_ascii: bool,
     separator_search: Arc<Option<Vec<u8>>>,
@@ -61,7 +66,9 @@ impl Default for Config {
             max_columns: None,
             max_columns_preview: false,
             max_matches: None,
+            replacement: Arc::new(None),
             column: false,
+            capture_labels: false,
             byte_offset: false,
             trim_ascii: false,
             separator_search: Arc::new(None),
@@ -91,6 +98,7 @@ impl StandardBuilder {
             config: self.config.clone(),
             wtr: RefCell::new(CounterWriter::new(wtr)),
             matches: vec![],
+            replacer: Replacer::new(),
         }
     }
 
@@ -98,19 +106,16 @@ impl StandardBuilder {
         self.build(NoColor::new(wtr))
     }
 
-    #[allow(dead_code)]
     pub fn color_specs(&mut self, specs: ColorSpecs) -> &mut StandardBuilder {
         self.config.colors = specs;
         self
     }
 
-    #[allow(dead_code)]
     pub fn stats(&mut self, yes: bool) -> &mut StandardBuilder {
         self.config.stats = yes;
         self
     }
 
-    #[allow(dead_code)]
     pub fn heading(&mut self, yes: bool) -> &mut StandardBuilder {
         self.config.heading = yes;
         self
@@ -138,29 +143,38 @@ impl StandardBuilder {
         self
     }
 
-    #[allow(dead_code)]
     pub fn max_columns(&mut self, limit: Option<u64>) -> &mut StandardBuilder {
         self.config.max_columns = limit;
         self
     }
 
-    #[allow(dead_code)]
     pub fn max_columns_preview(&mut self, yes: bool) -> &mut StandardBuilder {
         self.config.max_columns_preview = yes;
         self
     }
 
-    #[allow(dead_code)]
     pub fn max_matches(&mut self, limit: Option<u64>) -> &mut StandardBuilder {
         self.config.max_matches = limit;
         self
     }
 
+    pub fn replacement(&mut self, replacement: Option<ReplaceTemplate>) -> &mut StandardBuilder {
+        self.config.replacement = Arc::new(replacement);
+        self
+    }
+
     pub fn column(&mut self, yes: bool) -> &mut StandardBuilder {
         self.config.column = yes;
         self
     }
 
+    /// Prefix each line printed per match with the name of the match's
+    /// capture, eg when matching multiple captures.
+    pub fn capture_labels(&mut self, yes: bool) -> &mut StandardBuilder {
+        self.config.capture_labels = yes;
+        self
+    }
+
     #[allow(dead_code)]
     pub fn byte_offset(&mut self, yes: bool) -> &mut StandardBuilder {
         self.config.byte_offset = yes;
@@ -203,7 +217,6 @@ impl StandardBuilder {
         self
     }
 
-    #[allow(dead_code)]
     pub fn path_terminator(&mut self, terminator: Option<u8>) -> &mut StandardBuilder {
         self.config.path_terminator = terminator;
         self
@@ -215,6 +228,7 @@ pub struct Standard<W> {
     config: Config,
     wtr: RefCell<CounterWriter<W>>,
     matches: Vec<Match>,
+    replacer: Replacer,
 }
 
 impl<W: WriteColor> Standard<W> {
@@ -283,6 +297,7 @@ impl<W: WriteColor> Standard<W> {
             || self.config.column
             || self.config.per_match
             || self.config.only_matching
+            || self.config.replacement.is_some()
             || self.config.stats
     }
 }
@@ -326,7 +341,6 @@ impl<'p, 's, W: WriteColor> StandardSink<'p, 's, W> {
         self.match_count
     }
 
-    #[allow(dead_code)]
     pub fn stats(&self) -> Option<&Stats> {
         self.stats.as_ref()
     }
@@ -349,6 +363,13 @@ impl<'p, 's, W: WriteColor> StandardSink<'p, 's, W> {
         };
         self.match_count > limit
     }
+
+    fn replace(&mut self, mat: &SinkMatch<'_>) {
+        self.standard.replacer.clear();
+        if let Some(ref replacement) = *self.standard.config.replacement {
+            self.standard.replacer.replace_all(replacement, mat);
+        }
+    }
 }
 
 impl<'p, 's, W: WriteColor> Sink for StandardSink<'p, 's, W> {
@@ -363,16 +384,18 @@ impl<'p, 's, W: WriteColor> Sink for StandardSink<'p, 's, W> {
         }
 
         if let Some(ref mut stats) = self.stats {
-            stats.add_matches(self.standard.matches.len() as u64);
+            stats.add_matches(mat.matched_nodes().len() as u64);
             stats.add_matched_lines(mat.lines().count() as u64);
         }
 
+        self.replace(mat);
         StandardImpl::from_match(searcher, self, mat).sink()?;
         Ok(!self.should_quit())
     }
 
     fn context(&mut self, searcher: &Searcher, ctx: &SinkContext<'_>) -> Result<bool, io::Error> {
         self.standard.matches.clear();
+        self.standard.replacer.clear();
 
         if ctx.kind() == &SinkContextKind::After {
             self.after_context_remaining = self.after_context_remaining.saturating_sub(1);
@@ -403,13 +426,13 @@ impl<'p, 's, W: WriteColor> Sink for StandardSink<'p, 's, W> {
 
     fn finish(&mut self, _searcher: &Searcher, finish: &SinkFinish) -> Result<(), io::Error> {
         if let Some(stats) = self.stats.as_mut() {
-            stats.add_elapsed(self.start_time.elapsed());
-            stats.add_searches(1);
-            if self.match_count > 0 {
-                stats.add_searches_with_match(1);
-            }
-            stats.add_bytes_searched(finish.byte_count());
-            stats.add_bytes_printed(self.standard.wtr.borrow().count());
+            add_search_to_stats(
+                stats,
+                self.start_time.elapsed(),
+                finish,
+                self.match_count > 0,
+                self.standard.wtr.borrow().count(),
+            );
         }
         Ok(())
     }
@@ -420,6 +443,7 @@ struct StandardImpl<'a, W> {
     searcher: &'a Searcher,
     sink: &'a StandardSink<'a, 'a, W>,
     sunk: Sunk<'a>,
+    mat: Option<&'a SinkMatch<'a>>,
     in_color_match: Cell<bool>,
 }
 
@@ -429,6 +453,7 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
             searcher,
             sink,
             sunk: Sunk::empty(),
+            mat: None,
             in_color_match: Cell::new(false),
         }
     }
@@ -438,9 +463,11 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
         sink: &'a StandardSink<'_, '_, W>,
         mat: &'a SinkMatch<'a>,
     ) -> StandardImpl<'a, W> {
-        let sunk = Sunk::from_sink_match(mat, mat.exact_matches);
+        let sunk =
+            Sunk::from_sink_match(mat, mat.exact_matches, sink.standard.replacer.replacement());
         StandardImpl {
             sunk,
+            mat: Some(mat),
             ..StandardImpl::new(searcher, sink)
         }
     }
@@ -514,7 +541,7 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
                 self.write_prelude(
                     self.sunk.absolute_byte_offset() + m.start() as u64,
                     self.sunk.line_number(),
-                    Some(m.start() as u64 + 1),
+                    Some(self.column_number(0, m.start())),
                 )?;
 
                 let buf = &self.sunk.bytes()[m];
@@ -525,7 +552,7 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
                 self.write_prelude(
                     self.sunk.absolute_byte_offset() + m.start() as u64,
                     self.sunk.line_number(),
-                    Some(m.start() as u64 + 1),
+                    Some(self.column_number(0, m.start())),
                 )?;
                 self.write_colored_line(&[m], self.sunk.bytes())?;
             }
@@ -533,7 +560,7 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
             self.write_prelude(
                 self.sunk.absolute_byte_offset(),
                 self.sunk.line_number(),
-                Some(self.sunk.matches()[0].start() as u64 + 1),
+                Some(self.column_number(0, self.sunk.matches()[0].start())),
             )?;
             self.write_colored_line(self.sunk.matches(), self.sunk.bytes())?;
         }
@@ -544,7 +571,8 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
         debug_assert!(!self.sunk.matches().is_empty());
 
         if self.config().only_matching {
-            return self.sink_slow_multi_line_only_matching();
+            self.sink_slow_multi_line_only_matching()?;
+            return self.write_sexps(self.matched_nodes());
         } else if self.config().per_match {
             return self.sink_slow_multi_per_match();
         }
@@ -560,7 +588,7 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
             self.write_prelude(
                 self.sunk.absolute_byte_offset() + line.start() as u64,
                 self.sunk.line_number().map(|n| n + count),
-                Some(matches[0].start() as u64 + 1),
+                Some(self.column_number(0, matches[0].start())),
             )?;
             count += 1;
             if self.exceeds_max_columns(&bytes[line]) {
@@ -570,7 +598,7 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
                 self.write_line_term()?;
             }
         }
-        Ok(())
+        self.write_sexps(self.matched_nodes())
     }
 
     fn sink_slow_multi_line_only_matching(&self) -> io::Result<()> {
@@ -604,7 +632,7 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
                     self.write_prelude(
                         self.sunk.absolute_byte_offset() + m.start() as u64,
                         self.sunk.line_number().map(|n| n + count),
-                        Some(m.start() as u64 + 1),
+                        Some(self.column_number(0, m.start())),
                     )?;
 
                     let this_line = line.with_end(upto);
@@ -626,7 +654,9 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
         let line_term = self.searcher.line_terminator().as_byte();
         let spec = self.config().colors.matched();
         let bytes = self.sunk.bytes();
-        for &m in self.sunk.matches() {
+        let matched_nodes = self.sunk_matched_nodes();
+        for (index, &m) in self.sunk.matches().iter().enumerate() {
+            let matched_node = matched_nodes.get(index).copied();
             let mut count = 0;
             let mut stepper = LineStep::new(line_term, 0, bytes.len());
             while let Some((start, end)) = stepper.next(bytes) {
@@ -640,8 +670,11 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
                 self.write_prelude(
                     self.sunk.absolute_byte_offset() + line.start() as u64,
                     self.sunk.line_number().map(|n| n + count),
-                    Some(m.start().saturating_sub(line.start()) as u64 + 1),
+                    Some(self.column_number(line.start(), cmp::max(m.start(), line.start()))),
                 )?;
+                if let Some(matched_node) = matched_node {
+                    self.write_capture_label(matched_node, self.separator_field())?;
+                }
                 count += 1;
                 if self.exceeds_max_columns(&bytes[line]) {
                     self.write_exceeded_line(bytes, line, &[m], &mut 0)?;
@@ -669,6 +702,9 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
                     break;
                 }
             }
+            if let Some(matched_node) = matched_node {
+                self.write_sexps(std::slice::from_ref(matched_node))?;
+            }
         }
         Ok(())
     }
@@ -694,7 +730,10 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
             }
         }
         if self.config().byte_offset {
-            self.write_byte_offset(absolute_byte_offset, sep)?;
+            self.write_byte_offset(
+                self.searcher.original_byte_offset(absolute_byte_offset),
+                sep,
+            )?;
         }
         Ok(())
     }
@@ -705,10 +744,10 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
             let range = Match::new(0, line.len());
             self.write_exceeded_line(line, range, self.sunk.matches(), &mut 0)?;
         } else {
-            self.write_trim(line)?;
-            if !self.has_line_terminator(line) {
-                self.write_line_term()?;
-            }
+            let mut range = Match::new(0, line.len());
+            self.trim_line_terminator(line, &mut range);
+            self.write_trim(&line[range])?;
+            self.write_line_term()?;
         }
         Ok(())
     }
@@ -780,10 +819,16 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
     ) -> io::Result<()> {
         if self.config().max_columns_preview {
             let original = line;
+            let max_columns = self.config().max_columns.unwrap_or(0) as usize;
+            let start = preview_start(bytes, line, &matches[*match_index..], max_columns);
+            if start > line.start() {
+                self.write(b"[... omitted start of long line] ")?;
+                line = line.with_start(start);
+            }
             let end = bytes[line]
                 .grapheme_indices()
                 .map(|(_, end, _)| end)
-                .take(self.config().max_columns.unwrap_or(0) as usize)
+                .take(max_columns)
                 .last()
                 .unwrap_or(0)
                 + line.start();
@@ -898,6 +943,29 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
         Ok(())
     }
 
+    fn write_capture_label(
+        &self,
+        matched_node: &MatchedNode,
+        field_separator: &[u8],
+    ) -> io::Result<()> {
+        let mat = match self.mat {
+            Some(mat) if self.config().capture_labels => mat,
+            _ => return Ok(()),
+        };
+        self.write(b"@")?;
+        self.write(mat.capture_name(matched_node).as_bytes())?;
+        self.write(field_separator)?;
+        Ok(())
+    }
+
+    fn write_sexps(&self, matched_nodes: &[MatchedNode]) -> io::Result<()> {
+        for sexp in matched_nodes.iter().flat_map(MatchedNode::sexps) {
+            self.write(sexp.as_bytes())?;
+            self.write_line_term()?;
+        }
+        Ok(())
+    }
+
     fn write_byte_offset(&self, offset: u64, field_separator: &[u8]) -> io::Result<()> {
         let n = offset.to_string();
         self.write_spec(self.config().colors.column(), n.as_bytes())?;
@@ -905,8 +973,10 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
         Ok(())
     }
 
+    // lines are always printed with a plain \n, since the \r of a CRLF line
+    // terminator is trimmed along with it
     fn write_line_term(&self) -> io::Result<()> {
-        self.write(self.searcher.line_terminator().as_bytes())
+        self.write(&[self.searcher.line_terminator().as_byte()])
     }
 
     fn write_spec(&self, spec: &ColorSpec, buf: &[u8]) -> io::Result<()> {
@@ -954,14 +1024,29 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
         trim_line_terminator(self.searcher, buf, line);
     }
 
-    fn has_line_terminator(&self, buf: &[u8]) -> bool {
-        self.searcher.line_terminator().is_suffix(buf)
-    }
-
     fn is_context(&self) -> bool {
         self.sunk.context_kind().is_some()
     }
 
+    fn matched_nodes(&self) -> &'a [MatchedNode] {
+        self.mat.map(|mat| mat.matched_nodes()).unwrap_or_default()
+    }
+
+    /// The matched node of each of `self.sunk.matches()`.
+    fn sunk_matched_nodes(&self) -> Vec<&'a MatchedNode> {
+        let mat = match self.mat {
+            Some(mat) => mat,
+            None => return vec![],
+        };
+        if self.sink.standard.replacer.replacement().is_some() {
+            replaced_match_indices(mat.exact_matches)
+                .map(|index| &mat.matched_nodes()[index])
+                .collect()
+        } else {
+            mat.matched_nodes().iter().collect()
+        }
+    }
+
     fn config(&self) -> &'a Config {
         &self.sink.standard.config
     }
@@ -982,6 +1067,17 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
         }
     }
 
+    /// The 1-based column of `offset` in the line starting at `line_start`
+    /// (both relative to the sunk bytes), counted in bytes of the original
+    /// text.
+    fn column_number(&self, line_start: usize, offset: usize) -> u64 {
+        let original = |offset: usize| {
+            self.searcher
+                .original_byte_offset(self.sunk.absolute_byte_offset() + offset as u64)
+        };
+        original(offset) - original(line_start) + 1
+    }
+
     fn exceeds_max_columns(&self, line: &[u8]) -> bool {
         self.config()
             .max_columns
@@ -996,3 +1092,25 @@ impl<'a, W: WriteColor> StandardImpl<'a, W> {
         *range = trim_ascii_prefix(lineterm, slice, *range)
     }
 }
+
+fn preview_start(bytes: &[u8], line: Match, matches: &[Match], max_columns: usize) -> usize {
+    let first_match = match matches.iter().find(|m| m.end() > line.start()) {
+        Some(m) if m.start() > line.start() && m.start() < line.end() => m,
+        _ => return line.start(),
+    };
+    let leading = &bytes[line.start()..first_match.start()];
+    if leading.graphemes().count() < max_columns {
+        return line.start();
+    }
+    // keep a bit of the text leading up to the matched node in the preview
+    let leading_context_columns = max_columns / 4;
+    if leading_context_columns == 0 {
+        return first_match.start();
+    }
+    leading
+        .grapheme_indices()
+        .rev()
+        .take(leading_context_columns)
+        .last()
+        .map_or(first_match.start(), |(start, _, _)| line.start() + start)
+}
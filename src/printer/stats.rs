@@ -10,6 +10,8 @@ use super::util::NiceDuration;
 #[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize)]
 pub struct Stats {
     elapsed: NiceDuration,
+    elapsed_parsing: NiceDuration,
+    elapsed_querying: NiceDuration,
     searches: u64,
     searches_with_match: u64,
     bytes_searched: u64,
@@ -32,6 +34,8 @@ impl<'a> Add<&'a Stats> for Stats {
     fn add(self, rhs: &'a Stats) -> Stats {
         Stats {
             elapsed: NiceDuration(self.elapsed.0 + rhs.elapsed.0),
+            elapsed_parsing: NiceDuration(self.elapsed_parsing.0 + rhs.elapsed_parsing.0),
+            elapsed_querying: NiceDuration(self.elapsed_querying.0 + rhs.elapsed_querying.0),
             searches: self.searches + rhs.searches,
             searches_with_match: self.searches_with_match + rhs.searches_with_match,
             bytes_searched: self.bytes_searched + rhs.bytes_searched,
@@ -51,6 +55,8 @@ impl AddAssign for Stats {
 impl<'a> AddAssign<&'a Stats> for Stats {
     fn add_assign(&mut self, rhs: &'a Stats) {
         self.elapsed.0 += rhs.elapsed.0;
+        self.elapsed_parsing.0 += rhs.elapsed_parsing.0;
+        self.elapsed_querying.0 += rhs.elapsed_querying.0;
         self.searches += rhs.searches;
         self.searches_with_match += rhs.searches_with_match;
         self.bytes_searched += rhs.bytes_searched;
@@ -65,37 +71,38 @@ impl Stats {
         Stats::default()
     }
 
-    #[allow(dead_code)]
     pub fn elapsed(&self) -> Duration {
         self.elapsed.0
     }
 
-    #[allow(dead_code)]
+    pub fn elapsed_parsing(&self) -> Duration {
+        self.elapsed_parsing.0
+    }
+
+    pub fn elapsed_querying(&self) -> Duration {
+        self.elapsed_querying.0
+    }
+
     pub fn searches(&self) -> u64 {
         self.searches
     }
 
-    #[allow(dead_code)]
     pub fn searches_with_match(&self) -> u64 {
         self.searches_with_match
     }
 
-    #[allow(dead_code)]
     pub fn bytes_searched(&self) -> u64 {
         self.bytes_searched
     }
 
-    #[allow(dead_code)]
     pub fn bytes_printed(&self) -> u64 {
         self.bytes_printed
     }
 
-    #[allow(dead_code)]
     pub fn matched_lines(&self) -> u64 {
         self.matched_lines
     }
 
-    #[allow(dead_code)]
     pub fn matches(&self) -> u64 {
         self.matches
     }
@@ -104,6 +111,14 @@ impl Stats {
         self.elapsed.0 += duration;
     }
 
+    pub fn add_elapsed_parsing(&mut self, duration: Duration) {
+        self.elapsed_parsing.0 += duration;
+    }
+
+    pub fn add_elapsed_querying(&mut self, duration: Duration) {
+        self.elapsed_querying.0 += duration;
+    }
+
     pub fn add_searches(&mut self, n: u64) {
         self.searches += n;
     }
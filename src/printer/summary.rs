@@ -0,0 +1,292 @@
+// derived from https://github.com/BurntSushi/ripgrep/blob/master/crates/printer/src/summary.rs
+
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    path::Path,
+    sync::Arc,
+    time::Instant,
+};
+
+use termcolor::{ColorSpec, WriteColor};
+
+use super::{
+    color::ColorSpecs,
+    counter::CounterWriter,
+    stats::Stats,
+    util::{add_search_to_stats, PrinterPath},
+};
+use crate::{
+    searcher::Searcher,
+    sink::{Sink, SinkFinish, SinkMatch},
+};
+
+#[derive(Debug, Clone)]
+struct Config {
+    kind: SummaryKind,
+    colors: ColorSpecs,
+    stats: bool,
+    max_matches: Option<u64>,
+    separator_field: Arc<Vec<u8>>,
+    path_terminator: Option<u8>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            kind: SummaryKind::Count,
+            colors: ColorSpecs::default(),
+            stats: false,
+            max_matches: None,
+            separator_field: Arc::new(b":".to_vec()),
+            path_terminator: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SummaryKind {
+    Count,
+    CountMatches,
+    PathWithMatch,
+    PathWithoutMatch,
+}
+
+impl SummaryKind {
+    pub fn quit_early(&self) -> bool {
+        matches!(
+            *self,
+            SummaryKind::PathWithMatch | SummaryKind::PathWithoutMatch
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SummaryBuilder {
+    config: Config,
+}
+
+impl SummaryBuilder {
+    pub fn new() -> SummaryBuilder {
+        SummaryBuilder {
+            config: Config::default(),
+        }
+    }
+
+    pub fn build<W: WriteColor>(&self, wtr: W) -> Summary<W> {
+        Summary {
+            config: self.config.clone(),
+            wtr: RefCell::new(CounterWriter::new(wtr)),
+        }
+    }
+
+    pub fn kind(&mut self, kind: SummaryKind) -> &mut SummaryBuilder {
+        self.config.kind = kind;
+        self
+    }
+
+    pub fn color_specs(&mut self, specs: ColorSpecs) -> &mut SummaryBuilder {
+        self.config.colors = specs;
+        self
+    }
+
+    pub fn stats(&mut self, yes: bool) -> &mut SummaryBuilder {
+        self.config.stats = yes;
+        self
+    }
+
+    pub fn max_matches(&mut self, limit: Option<u64>) -> &mut SummaryBuilder {
+        self.config.max_matches = limit;
+        self
+    }
+
+    pub fn path_terminator(&mut self, terminator: Option<u8>) -> &mut SummaryBuilder {
+        self.config.path_terminator = terminator;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct Summary<W> {
+    config: Config,
+    wtr: RefCell<CounterWriter<W>>,
+}
+
+impl<W: WriteColor> Summary<W> {
+    #[allow(dead_code)]
+    pub fn new(wtr: W) -> Summary<W> {
+        SummaryBuilder::new().build(wtr)
+    }
+
+    pub fn sink_with_path<'p, 's, P>(&'s mut self, path: &'p P) -> SummarySink<'p, 's, W>
+    where
+        P: ?Sized + AsRef<Path>,
+    {
+        let stats = if self.config.stats {
+            Some(Stats::new())
+        } else {
+            None
+        };
+        SummarySink {
+            summary: self,
+            path: PrinterPath::new(path.as_ref()),
+            start_time: Instant::now(),
+            match_count: 0,
+            sink_match_count: 0,
+            stats,
+        }
+    }
+}
+
+impl<W> Summary<W> {
+    pub fn get_mut(&mut self) -> &mut W {
+        self.wtr.get_mut().get_mut()
+    }
+
+    #[allow(dead_code)]
+    pub fn into_inner(self) -> W {
+        self.wtr.into_inner().into_inner()
+    }
+}
+
+#[derive(Debug)]
+pub struct SummarySink<'p, 's, W> {
+    summary: &'s mut Summary<W>,
+    path: PrinterPath<'p>,
+    start_time: Instant,
+    match_count: u64,
+    sink_match_count: u64,
+    stats: Option<Stats>,
+}
+
+impl<'p, 's, W: WriteColor> SummarySink<'p, 's, W> {
+    pub fn has_match(&self) -> bool {
+        match self.summary.config.kind {
+            SummaryKind::PathWithoutMatch => self.match_count == 0,
+            _ => self.match_count > 0,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn match_count(&self) -> u64 {
+        self.match_count
+    }
+
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+
+    fn should_quit(&self) -> bool {
+        if self.summary.config.kind.quit_early() {
+            return true;
+        }
+        match self.summary.config.max_matches {
+            None => false,
+            Some(limit) => self.sink_match_count >= limit,
+        }
+    }
+
+    fn write_summary(&self, searcher: &Searcher) -> io::Result<()> {
+        match self.summary.config.kind {
+            SummaryKind::Count | SummaryKind::CountMatches => {
+                if self.match_count == 0 {
+                    return Ok(());
+                }
+                self.write_path_field()?;
+                let n = self.match_count.to_string();
+                self.write(n.as_bytes())?;
+                self.write_line_term(searcher)
+            }
+            SummaryKind::PathWithMatch => {
+                if self.match_count == 0 {
+                    return Ok(());
+                }
+                self.write_path_line(searcher)
+            }
+            SummaryKind::PathWithoutMatch => {
+                if self.match_count > 0 {
+                    return Ok(());
+                }
+                self.write_path_line(searcher)
+            }
+        }
+    }
+
+    fn write_path_line(&self, searcher: &Searcher) -> io::Result<()> {
+        self.write_spec(self.summary.config.colors.path(), self.path.as_bytes())?;
+        if let Some(term) = self.summary.config.path_terminator {
+            self.write(&[term])
+        } else {
+            self.write_line_term(searcher)
+        }
+    }
+
+    fn write_path_field(&self) -> io::Result<()> {
+        self.write_spec(self.summary.config.colors.path(), self.path.as_bytes())?;
+        if let Some(term) = self.summary.config.path_terminator {
+            self.write(&[term])
+        } else {
+            self.write(&self.summary.config.separator_field)
+        }
+    }
+
+    // a plain \n even for CRLF, like the standard printer
+    fn write_line_term(&self, searcher: &Searcher) -> io::Result<()> {
+        self.write(&[searcher.line_terminator().as_byte()])
+    }
+
+    fn write_spec(&self, spec: &ColorSpec, buf: &[u8]) -> io::Result<()> {
+        let mut wtr = self.summary.wtr.borrow_mut();
+        wtr.set_color(spec)?;
+        wtr.write_all(buf)?;
+        wtr.reset()?;
+        Ok(())
+    }
+
+    fn write(&self, buf: &[u8]) -> io::Result<()> {
+        self.summary.wtr.borrow_mut().write_all(buf)
+    }
+}
+
+impl<'p, 's, W: WriteColor> Sink for SummarySink<'p, 's, W> {
+    type Error = io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, io::Error> {
+        self.match_count += match self.summary.config.kind {
+            SummaryKind::Count => mat.lines().count() as u64,
+            SummaryKind::CountMatches => mat.matched_nodes().len() as u64,
+            SummaryKind::PathWithMatch | SummaryKind::PathWithoutMatch => 1,
+        };
+        self.sink_match_count += 1;
+        if let Some(ref mut stats) = self.stats {
+            stats.add_matches(mat.matched_nodes().len() as u64);
+            stats.add_matched_lines(mat.lines().count() as u64);
+        }
+        Ok(!self.should_quit())
+    }
+
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
+        self.summary.wtr.borrow_mut().reset_count();
+        self.start_time = Instant::now();
+        self.match_count = 0;
+        self.sink_match_count = 0;
+        if self.summary.config.max_matches == Some(0) {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn finish(&mut self, searcher: &Searcher, finish: &SinkFinish) -> Result<(), io::Error> {
+        self.write_summary(searcher)?;
+        if let Some(stats) = self.stats.as_mut() {
+            add_search_to_stats(
+                stats,
+                self.start_time.elapsed(),
+                finish,
+                self.match_count > 0,
+                self.summary.wtr.borrow().count(),
+            );
+        }
+        Ok(())
+    }
+}
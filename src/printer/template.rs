@@ -0,0 +1,360 @@
+use std::{
+    cell::RefCell,
+    error, fmt,
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+    time::Instant,
+};
+
+use termcolor::WriteColor;
+
+use super::{
+    counter::CounterWriter,
+    stats::Stats,
+    util::{add_search_to_stats, PrinterPath},
+};
+use crate::{
+    language::SupportedLanguage,
+    searcher::Searcher,
+    sink::{MatchedNode, Sink, SinkFinish, SinkMatch},
+};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TemplateError {
+    UnrecognizedPlaceholder(String),
+    UnclosedPlaceholder(String),
+    UnmatchedClosingBrace(String),
+}
+
+impl error::Error for TemplateError {}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            TemplateError::UnrecognizedPlaceholder(ref name) => write!(
+                f,
+                "unrecognized placeholder '{{{}}}'. Choose from: \
+                     path, language, kind, capture, text, start_line, \
+                     end_line, start_column, end_column, start_byte, \
+                     end_byte, @{{capture name}}.",
+                name,
+            ),
+            TemplateError::UnclosedPlaceholder(ref original) => write!(
+                f,
+                "unclosed placeholder in template '{}'. Use '{{{{' for a \
+                     literal '{{'.",
+                original,
+            ),
+            TemplateError::UnmatchedClosingBrace(ref original) => write!(
+                f,
+                "unmatched '}}' in template '{}'. Use '}}}}' for a literal \
+                     '}}'.",
+                original,
+            ),
+        }
+    }
+}
+
+/// A parsed --format-template, eg `{path}:{start_line}:{@name}`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FormatTemplate {
+    pieces: Vec<Piece>,
+}
+
+impl FormatTemplate {
+    /// The names of the captures referenced by `{@name}` placeholders.
+    pub fn capture_names(&self) -> impl Iterator<Item = &str> {
+        self.pieces.iter().filter_map(|piece| match piece {
+            Piece::Placeholder(Placeholder::Capture(capture_name)) => Some(&**capture_name),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Piece {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Placeholder {
+    Path,
+    Language,
+    Kind,
+    CaptureName,
+    Text,
+    StartLine,
+    EndLine,
+    StartColumn,
+    EndColumn,
+    StartByte,
+    EndByte,
+    Capture(String),
+}
+
+impl FromStr for FormatTemplate {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<FormatTemplate, TemplateError> {
+        let mut pieces = vec![];
+        let mut literal = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| TemplateError::UnclosedPlaceholder(s.to_owned()))?;
+                    let placeholder: Placeholder = rest[..end].parse()?;
+                    chars = rest[end + 1..].chars();
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                    }
+                    pieces.push(Piece::Placeholder(placeholder));
+                }
+                '}' => return Err(TemplateError::UnmatchedClosingBrace(s.to_owned())),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+        Ok(FormatTemplate { pieces })
+    }
+}
+
+impl FromStr for Placeholder {
+    type Err = TemplateError;
+
+    fn from_str(s: &str) -> Result<Placeholder, TemplateError> {
+        if let Some(capture_name) = s.strip_prefix('@') {
+            if capture_name.is_empty() {
+                return Err(TemplateError::UnrecognizedPlaceholder(s.to_owned()));
+            }
+            return Ok(Placeholder::Capture(capture_name.to_owned()));
+        }
+        match s {
+            "path" => Ok(Placeholder::Path),
+            "language" => Ok(Placeholder::Language),
+            "kind" => Ok(Placeholder::Kind),
+            "capture" => Ok(Placeholder::CaptureName),
+            "text" => Ok(Placeholder::Text),
+            "start_line" => Ok(Placeholder::StartLine),
+            "end_line" => Ok(Placeholder::EndLine),
+            "start_column" => Ok(Placeholder::StartColumn),
+            "end_column" => Ok(Placeholder::EndColumn),
+            "start_byte" => Ok(Placeholder::StartByte),
+            "end_byte" => Ok(Placeholder::EndByte),
+            _ => Err(TemplateError::UnrecognizedPlaceholder(s.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Config {
+    template: FormatTemplate,
+    stats: bool,
+    max_matches: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TemplateBuilder {
+    config: Config,
+}
+
+impl TemplateBuilder {
+    pub fn new(template: FormatTemplate) -> TemplateBuilder {
+        TemplateBuilder {
+            config: Config {
+                template,
+                stats: false,
+                max_matches: None,
+            },
+        }
+    }
+
+    pub fn build<W: WriteColor>(&self, wtr: W) -> Template<W> {
+        Template {
+            config: self.config.clone(),
+            wtr: RefCell::new(CounterWriter::new(wtr)),
+        }
+    }
+
+    pub fn stats(&mut self, yes: bool) -> &mut TemplateBuilder {
+        self.config.stats = yes;
+        self
+    }
+
+    pub fn max_matches(&mut self, limit: Option<u64>) -> &mut TemplateBuilder {
+        self.config.max_matches = limit;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct Template<W> {
+    config: Config,
+    wtr: RefCell<CounterWriter<W>>,
+}
+
+impl<W: WriteColor> Template<W> {
+    pub fn sink_with_path<'p, 's, P>(
+        &'s mut self,
+        path: &'p P,
+        language: SupportedLanguage,
+    ) -> TemplateSink<'p, 's, W>
+    where
+        P: ?Sized + AsRef<Path>,
+    {
+        let stats = if self.config.stats {
+            Some(Stats::new())
+        } else {
+            None
+        };
+        TemplateSink {
+            template: self,
+            path: PrinterPath::new(path.as_ref()),
+            language,
+            start_time: Instant::now(),
+            match_count: 0,
+            stats,
+        }
+    }
+}
+
+impl<W> Template<W> {
+    pub fn get_mut(&mut self) -> &mut W {
+        self.wtr.get_mut().get_mut()
+    }
+}
+
+#[derive(Debug)]
+pub struct TemplateSink<'p, 's, W> {
+    template: &'s mut Template<W>,
+    path: PrinterPath<'p>,
+    language: SupportedLanguage,
+    start_time: Instant,
+    match_count: u64,
+    stats: Option<Stats>,
+}
+
+impl<'p, 's, W: WriteColor> TemplateSink<'p, 's, W> {
+    pub fn has_match(&self) -> bool {
+        self.match_count > 0
+    }
+
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+
+    fn should_quit(&self) -> bool {
+        match self.template.config.max_matches {
+            None => false,
+            Some(limit) => self.match_count >= limit,
+        }
+    }
+
+    fn write_node(
+        &self,
+        searcher: &Searcher,
+        mat: &SinkMatch<'_>,
+        matched_node: &MatchedNode,
+    ) -> io::Result<()> {
+        let range = matched_node.range();
+        for piece in &self.template.config.template.pieces {
+            let placeholder = match piece {
+                Piece::Literal(literal) => {
+                    self.write(literal.as_bytes())?;
+                    continue;
+                }
+                Piece::Placeholder(placeholder) => placeholder,
+            };
+            match placeholder {
+                Placeholder::Path => self.write(self.path.as_bytes())?,
+                Placeholder::Language => self.write(self.language.to_string().as_bytes())?,
+                Placeholder::Kind => self.write(matched_node.kind().as_bytes())?,
+                Placeholder::CaptureName => {
+                    self.write(mat.capture_name(matched_node).as_bytes())?
+                }
+                Placeholder::Text => self.write(&mat.buffer()[range])?,
+                Placeholder::StartLine => self.write_number(matched_node.start_point().row + 1)?,
+                Placeholder::EndLine => self.write_number(matched_node.end_point().row + 1)?,
+                Placeholder::StartColumn => {
+                    self.write_number(matched_node.start_point().column + 1)?
+                }
+                Placeholder::EndColumn => self.write_number(matched_node.end_point().column + 1)?,
+                Placeholder::StartByte => {
+                    self.write_number(searcher.original_byte_offset(range.start() as u64) as usize)?
+                }
+                Placeholder::EndByte => {
+                    self.write_number(searcher.original_byte_offset(range.end() as u64) as usize)?
+                }
+                Placeholder::Capture(capture_name) => {
+                    if let Some(capture_range) = mat.capture_range(matched_node, capture_name) {
+                        self.write(&mat.buffer()[capture_range])?;
+                    }
+                }
+            }
+        }
+        self.write(b"\n")
+    }
+
+    fn write_number(&self, n: usize) -> io::Result<()> {
+        self.write(n.to_string().as_bytes())
+    }
+
+    fn write(&self, buf: &[u8]) -> io::Result<()> {
+        self.template.wtr.borrow_mut().write_all(buf)
+    }
+}
+
+impl<'p, 's, W: WriteColor> Sink for TemplateSink<'p, 's, W> {
+    type Error = io::Error;
+
+    fn matched(&mut self, searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, io::Error> {
+        self.match_count += 1;
+        if let Some(ref mut stats) = self.stats {
+            stats.add_matches(mat.matched_nodes().len() as u64);
+            stats.add_matched_lines(mat.lines().count() as u64);
+        }
+
+        for matched_node in mat.matched_nodes() {
+            self.write_node(searcher, mat, matched_node)?;
+        }
+        Ok(!self.should_quit())
+    }
+
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
+        self.template.wtr.borrow_mut().reset_count();
+        self.start_time = Instant::now();
+        self.match_count = 0;
+        if self.template.config.max_matches == Some(0) {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    fn finish(&mut self, _searcher: &Searcher, finish: &SinkFinish) -> Result<(), io::Error> {
+        if let Some(stats) = self.stats.as_mut() {
+            add_search_to_stats(
+                stats,
+                self.start_time.elapsed(),
+                finish,
+                self.match_count > 0,
+                self.template.wtr.borrow().count(),
+            );
+        }
+        Ok(())
+    }
+}
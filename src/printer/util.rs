@@ -1,17 +1,194 @@
 // derived from https://github.com/BurntSushi/ripgrep/blob/master/crates/printer/src/util.rs
 
-use std::{borrow::Cow, fmt, path::Path, time};
+use std::{borrow::Cow, error, fmt, path::Path, str::FromStr, time};
 
 use bstr::{ByteSlice, ByteVec};
 use serde::{Serialize, Serializer};
 
+use super::stats::Stats;
 use crate::{
     lines::LineIter,
     matcher::{LineTerminator, Match},
     searcher::Searcher,
-    sink::{SinkContext, SinkContextKind, SinkMatch},
+    sink::{MatchedNode, SinkContext, SinkContextKind, SinkFinish, SinkMatch},
 };
 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ReplaceTemplateError {
+    UnclosedCaptureReference(String),
+}
+
+impl error::Error for ReplaceTemplateError {}
+
+impl fmt::Display for ReplaceTemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ReplaceTemplateError::UnclosedCaptureReference(ref original) => write!(
+                f,
+                "unclosed '${{' in replacement '{}'. Use '$$' for a literal \
+                     '$'.",
+                original,
+            ),
+        }
+    }
+}
+
+/// A parsed -r/--replace, eg `${name}_renamed`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReplaceTemplate {
+    pieces: Vec<ReplacePiece>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum ReplacePiece {
+    Literal(Vec<u8>),
+    Capture(String),
+}
+
+impl ReplaceTemplate {
+    /// The names of the captures referenced by `$name`/`${name}`.
+    pub fn capture_names(&self) -> impl Iterator<Item = &str> {
+        self.pieces.iter().filter_map(|piece| match piece {
+            ReplacePiece::Capture(capture_name) => Some(&**capture_name),
+            _ => None,
+        })
+    }
+
+    pub fn interpolate(&self, mat: &SinkMatch<'_>, matched_node: &MatchedNode, dst: &mut Vec<u8>) {
+        for piece in &self.pieces {
+            match piece {
+                ReplacePiece::Literal(literal) => dst.extend_from_slice(literal),
+                ReplacePiece::Capture(capture_name) => {
+                    if let Some(capture_range) = mat.capture_range(matched_node, capture_name) {
+                        dst.extend_from_slice(&mat.buffer()[capture_range]);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for ReplaceTemplate {
+    type Err = ReplaceTemplateError;
+
+    fn from_str(s: &str) -> Result<ReplaceTemplate, ReplaceTemplateError> {
+        fn is_name_char(c: char) -> bool {
+            c == '_' || c.is_ascii_alphanumeric()
+        }
+
+        let mut pieces = vec![];
+        let mut literal = String::new();
+        let mut rest = s;
+        while let Some(dollar) = rest.find('$') {
+            literal.push_str(&rest[..dollar]);
+            rest = &rest[dollar + 1..];
+            let capture_name = if let Some(braced) = rest.strip_prefix('{') {
+                let end = braced
+                    .find('}')
+                    .ok_or_else(|| ReplaceTemplateError::UnclosedCaptureReference(s.to_owned()))?;
+                rest = &braced[end + 1..];
+                &braced[..end]
+            } else if let Some(after) = rest.strip_prefix('$') {
+                literal.push('$');
+                rest = after;
+                continue;
+            } else {
+                let end = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+                let capture_name = &rest[..end];
+                rest = &rest[end..];
+                capture_name
+            };
+            if capture_name.is_empty() {
+                literal.push('$');
+                continue;
+            }
+            if !literal.is_empty() {
+                pieces.push(ReplacePiece::Literal(
+                    std::mem::take(&mut literal).into_bytes(),
+                ));
+            }
+            pieces.push(ReplacePiece::Capture(capture_name.to_owned()));
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            pieces.push(ReplacePiece::Literal(literal.into_bytes()));
+        }
+        Ok(ReplaceTemplate { pieces })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Replacer {
+    space: Option<(Vec<u8>, Vec<Match>)>,
+}
+
+impl Replacer {
+    pub fn new() -> Replacer {
+        Replacer { space: None }
+    }
+
+    /// Replaces each matching node in the matched lines of `mat` with the
+    /// interpolated `template`, recording where each replacement ended up.
+    pub fn replace_all(&mut self, template: &ReplaceTemplate, mat: &SinkMatch<'_>) {
+        let (dst, matches) = self.allocate();
+        let bytes = mat.bytes();
+        let mut last_end = 0;
+        for index in replaced_match_indices(mat.exact_matches) {
+            let m = mat.exact_matches[index];
+            dst.extend_from_slice(&bytes[last_end..m.start()]);
+            let start = dst.len();
+            template.interpolate(mat, &mat.matched_nodes()[index], dst);
+            matches.push(Match::new(start, dst.len()));
+            last_end = m.end();
+        }
+        dst.extend_from_slice(&bytes[last_end..]);
+    }
+
+    pub fn clear(&mut self) {
+        if let Some((ref mut dst, ref mut matches)) = self.space {
+            dst.clear();
+            matches.clear();
+        }
+    }
+
+    pub fn replacement(&self) -> Option<(&[u8], &[Match])> {
+        match self.space {
+            None => None,
+            Some((ref dst, ref matches)) => {
+                if dst.is_empty() && matches.is_empty() {
+                    None
+                } else {
+                    Some((dst, matches))
+                }
+            }
+        }
+    }
+
+    fn allocate(&mut self) -> (&mut Vec<u8>, &mut Vec<Match>) {
+        if self.space.is_none() {
+            self.space = Some((vec![], vec![]));
+        }
+        let (ref mut dst, ref mut matches) = *self.space.as_mut().unwrap();
+        dst.clear();
+        matches.clear();
+        (dst, matches)
+    }
+}
+
+/// The indices of the matches that [`Replacer::replace_all`] replaces, in the
+/// same order as the replacements it records.
+pub fn replaced_match_indices(matches: &[Match]) -> impl Iterator<Item = usize> + '_ {
+    let mut last_end = 0;
+    matches.iter().enumerate().filter_map(move |(index, m)| {
+        // a node nested inside an already-replaced node is gone
+        if m.start() < last_end {
+            return None;
+        }
+        last_end = m.end();
+        Some(index)
+    })
+}
+
 #[derive(Debug)]
 pub struct Sunk<'a> {
     bytes: &'a [u8],
@@ -36,8 +213,12 @@ impl<'a> Sunk<'a> {
     }
 
     #[inline]
-    pub fn from_sink_match(sunk: &'a SinkMatch<'a>, original_matches: &'a [Match]) -> Sunk<'a> {
-        let (bytes, matches) = (sunk.bytes(), original_matches);
+    pub fn from_sink_match(
+        sunk: &'a SinkMatch<'a>,
+        original_matches: &'a [Match],
+        replacement: Option<(&'a [u8], &'a [Match])>,
+    ) -> Sunk<'a> {
+        let (bytes, matches) = replacement.unwrap_or_else(|| (sunk.bytes(), original_matches));
         Sunk {
             bytes,
             absolute_byte_offset: sunk.absolute_byte_offset(),
@@ -161,6 +342,25 @@ impl Serialize for NiceDuration {
     }
 }
 
+/// Adds a finished search of a single file to `stats`.
+pub fn add_search_to_stats(
+    stats: &mut Stats,
+    elapsed: time::Duration,
+    finish: &SinkFinish,
+    has_match: bool,
+    bytes_printed: u64,
+) {
+    stats.add_elapsed(elapsed);
+    stats.add_elapsed_parsing(finish.elapsed_parsing());
+    stats.add_elapsed_querying(finish.elapsed_querying());
+    stats.add_searches(1);
+    if has_match {
+        stats.add_searches_with_match(1);
+    }
+    stats.add_bytes_searched(finish.byte_count());
+    stats.add_bytes_printed(bytes_printed);
+}
+
 pub fn trim_ascii_prefix(line_term: LineTerminator, slice: &[u8], range: Match) -> Match {
     fn is_space(b: u8) -> bool {
         matches!(b, b'\t' | b'\n' | b'\x0B' | b'\x0C' | b'\r' | b' ')
@@ -1,5 +1,11 @@
 use std::{
-    sync::{mpsc, mpsc::Receiver, Arc, Mutex},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+        mpsc::Receiver,
+        Arc, Mutex,
+    },
     thread,
     thread::JoinHandle,
 };
@@ -21,8 +27,9 @@ use crate::{
 pub(crate) fn into_parallel_iterator(
     walk_parallel: WalkParallel,
     non_fatal_errors: Arc<Mutex<Vec<NonFatalError>>>,
+    quit: Arc<AtomicBool>,
 ) -> IterBridge<WalkParallelIterator> {
-    WalkParallelIterator::new(walk_parallel, non_fatal_errors).par_bridge()
+    WalkParallelIterator::new(walk_parallel, non_fatal_errors, quit).par_bridge()
 }
 
 pub(crate) struct WalkParallelIterator {
@@ -34,6 +41,7 @@ impl WalkParallelIterator {
     pub fn new(
         walk_parallel: WalkParallel,
         non_fatal_errors: Arc<Mutex<Vec<NonFatalError>>>,
+        quit: Arc<AtomicBool>,
     ) -> Self {
         let (sender, receiver) = mpsc::channel::<(DirEntry, Vec<SupportedLanguage>)>();
         let handle = thread::spawn(move || {
@@ -42,7 +50,11 @@ impl WalkParallelIterator {
                 Box::new({
                     let sender = sender.clone();
                     let non_fatal_errors = non_fatal_errors.clone();
+                    let quit = quit.clone();
                     move |entry_and_match_metadata| {
+                        if quit.load(Ordering::SeqCst) {
+                            return WalkState::Quit;
+                        }
                         let (entry, match_metadata) = match entry_and_match_metadata {
                             Err(err) => {
                                 non_fatal_errors.lock().unwrap().push(err.into());
@@ -107,3 +119,17 @@ pub(crate) fn get_project_file_walker_types(language: Option<SupportedLanguage>)
     }
     types_builder.build().unwrap()
 }
+
+/// The languages whose file types match `path`, the same way that the walker
+/// matches the files it finds.
+pub(crate) fn get_matched_languages_for_path(path: &Path) -> Vec<SupportedLanguage> {
+    ALL_SUPPORTED_LANGUAGES
+        .values()
+        .copied()
+        .filter(|&language| {
+            get_project_file_walker_types(Some(language))
+                .matched(path, false)
+                .is_whitelist()
+        })
+        .collect()
+}
@@ -1,12 +1,12 @@
 use std::sync::Arc;
 
-use tree_sitter::{Language, Query};
+use tree_sitter::{CaptureQuantifier, Language, Query};
 
 use crate::plugin::Filterer;
 
 pub struct QueryContext {
     pub query: Arc<Query>,
-    pub capture_index: u32,
+    pub capture_indices: Vec<u32>,
     pub language: Language,
     pub filter: Option<Arc<Filterer>>,
 }
@@ -15,7 +15,7 @@ impl std::fmt::Debug for QueryContext {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("QueryContext")
             .field("query", &self.query)
-            .field("capture_index", &self.capture_index)
+            .field("capture_indices", &self.capture_indices)
             .field("language", &self.language)
             // .field("filter", &self.filter)
             .finish()
@@ -25,15 +25,29 @@ impl std::fmt::Debug for QueryContext {
 impl QueryContext {
     pub fn new(
         query: Arc<Query>,
-        capture_index: u32,
+        capture_indices: Vec<u32>,
         language: Language,
         filter: Option<Arc<Filterer>>,
     ) -> Self {
         Self {
             query,
-            capture_index,
+            capture_indices,
             language,
             filter,
         }
     }
+
+    /// Whether any of the searched-for captures can capture more than one
+    /// node in a single match.
+    pub fn has_quantified_captures(&self) -> bool {
+        (0..self.query.pattern_count()).any(|pattern_index| {
+            let quantifiers = self.query.capture_quantifiers(pattern_index);
+            self.capture_indices.iter().any(|&capture_index| {
+                matches!(
+                    quantifiers[capture_index as usize],
+                    CaptureQuantifier::ZeroOrMore | CaptureQuantifier::OneOrMore
+                )
+            })
+        })
+    }
 }
@@ -1,10 +1,12 @@
 // derived from https://github.com/BurntSushi/ripgrep/blob/master/crates/searcher/src/searcher/core.rs
 
+use std::time::Duration;
+
 use crate::{
     lines::{self, LineStep},
     query_context::QueryContext,
     searcher::{Config, Range, Searcher},
-    sink::{Sink, SinkContext, SinkContextKind, SinkFinish, SinkMatch},
+    sink::{MatchedNode, Sink, SinkContext, SinkContextKind, SinkFinish, SinkMatch},
 };
 
 #[derive(Debug)]
@@ -61,16 +63,29 @@ impl<'s, S: Sink> Core<'s, S> {
         buf: &[u8],
         range: &Range,
         exact_matches: &[Range],
+        matched_nodes: &[MatchedNode],
     ) -> Result<bool, S::Error> {
-        self.sink_matched(buf, range, exact_matches)
+        self.sink_matched(buf, range, exact_matches, matched_nodes)
     }
 
     pub fn begin(&mut self) -> Result<bool, S::Error> {
         self.sink.begin(self.searcher)
     }
 
-    pub fn finish(&mut self, byte_count: u64) -> Result<(), S::Error> {
-        self.sink.finish(self.searcher, &SinkFinish { byte_count })
+    pub fn finish(
+        &mut self,
+        byte_count: u64,
+        elapsed_parsing: Duration,
+        elapsed_querying: Duration,
+    ) -> Result<(), S::Error> {
+        self.sink.finish(
+            self.searcher,
+            &SinkFinish {
+                byte_count,
+                elapsed_parsing,
+                elapsed_querying,
+            },
+        )
     }
 
     pub fn before_context_by_line(&mut self, buf: &[u8], upto: usize) -> Result<bool, S::Error> {
@@ -138,6 +153,7 @@ impl<'s, S: Sink> Core<'s, S> {
         buf: &[u8],
         range: &Range,
         exact_matches: &[Range],
+        matched_nodes: &[MatchedNode],
     ) -> Result<bool, S::Error> {
         if !self.sink_break_context(range.start())? {
             return Ok(false);
@@ -155,6 +171,8 @@ impl<'s, S: Sink> Core<'s, S> {
                 buffer: buf,
                 bytes_range_in_buffer: range.start()..range.end(),
                 exact_matches,
+                matched_nodes,
+                capture_names: self.query_context.query.capture_names(),
             },
         )?;
         if !keepgoing {
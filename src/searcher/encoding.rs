@@ -0,0 +1,245 @@
+use std::cmp;
+
+use encoding_rs::CoderResult;
+
+use crate::searcher::ConfigError;
+
+/// An encoding to transcode searched text from, as identified by any of the
+/// labels in the [Encoding Standard](https://encoding.spec.whatwg.org/#concept-encoding-get)
+/// (eg `windows-1252` or `sjis`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Encoding(&'static encoding_rs::Encoding);
+
+impl Encoding {
+    pub fn new(label: &str) -> Result<Encoding, ConfigError> {
+        match encoding_rs::Encoding::for_label_no_replacement(label.as_bytes()) {
+            Some(encoding) => Ok(Encoding(encoding)),
+            None => Err(ConfigError::UnknownEncoding {
+                label: label.to_owned(),
+            }),
+        }
+    }
+
+    /// Whether ASCII text means the same in this encoding, which isn't true
+    /// of eg UTF-16.
+    pub fn is_ascii_compatible(&self) -> bool {
+        self.0.is_ascii_compatible()
+    }
+}
+
+/// Maps byte offsets in transcoded text back to the corresponding byte offsets
+/// in the original text. When nothing was transcoded, it maps every offset to
+/// itself.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OffsetMap {
+    // (transcoded offset, original offset) pairs, at each point after which
+    // the two offsets advance in lockstep again
+    anchors: Vec<(u64, u64)>,
+}
+
+impl OffsetMap {
+    pub fn is_identity(&self) -> bool {
+        self.anchors.is_empty()
+    }
+
+    pub fn original(&self, offset: u64) -> u64 {
+        let index = self
+            .anchors
+            .partition_point(|&(transcoded, _)| transcoded <= offset);
+        match index.checked_sub(1) {
+            None => offset,
+            Some(index) => {
+                let (transcoded, original) = self.anchors[index];
+                original + (offset - transcoded)
+            }
+        }
+    }
+
+    /// Maps the (0-based, byte) `column` of the transcoded text at `offset` to
+    /// a column in the original text.
+    pub fn original_column(&self, offset: usize, column: usize) -> usize {
+        (self.original(offset as u64) - self.original((offset - column) as u64)) as usize
+    }
+
+    fn push(&mut self, transcoded: usize, original: usize) {
+        self.anchors.push((transcoded as u64, original as u64));
+    }
+}
+
+pub(crate) struct Transcoded {
+    pub text: Vec<u8>,
+    pub offset_map: OffsetMap,
+    /// Whether the source wasn't UTF-8, as opposed to just starting with a
+    /// UTF-8 BOM.
+    pub is_transcoded: bool,
+}
+
+/// Transcodes `source` to UTF-8 from `encoding` (UTF-8 by default), or from
+/// the encoding indicated by its BOM when `bom_sniffing`, replacing malformed
+/// sequences with U+FFFD.
+///
+/// Text that's already UTF-8 is passed through as-is (apart from its BOM).
+pub(crate) fn transcode(
+    source: &[u8],
+    encoding: Option<Encoding>,
+    bom_sniffing: bool,
+) -> Transcoded {
+    let mut offset_map = OffsetMap::default();
+    let (encoding, bom_length) = match encoding_rs::Encoding::for_bom(source) {
+        Some((encoding, bom_length)) if bom_sniffing => {
+            offset_map.push(0, bom_length);
+            (encoding, bom_length)
+        }
+        _ => (
+            encoding.map_or(encoding_rs::UTF_8, |encoding| encoding.0),
+            0,
+        ),
+    };
+    if encoding == encoding_rs::UTF_8 {
+        return Transcoded {
+            text: source[bom_length..].to_vec(),
+            offset_map,
+            is_transcoded: false,
+        };
+    }
+
+    let mut transcoder = Transcoder {
+        decoder: encoding.new_decoder_without_bom_handling(),
+        text: Vec::with_capacity(source.len()),
+        offset_map,
+        original_start: bom_length,
+    };
+    if let Some(original_len) = fixed_original_char_len(encoding) {
+        transcoder.decode_all(source, original_len);
+    } else {
+        transcoder.decode_by_char(source, encoding.is_ascii_compatible());
+    }
+    let Transcoder {
+        text, offset_map, ..
+    } = transcoder;
+    Transcoded {
+        text,
+        offset_map,
+        is_transcoded: true,
+    }
+}
+
+/// For encodings where the length of each original character follows from
+/// the length of its UTF-8 encoding, that mapping.
+fn fixed_original_char_len(encoding: &'static encoding_rs::Encoding) -> Option<fn(usize) -> usize> {
+    if encoding.is_single_byte() {
+        // including the U+FFFD that an unmapped byte gets decoded to
+        Some(|_| 1)
+    } else if encoding == encoding_rs::UTF_16LE || encoding == encoding_rs::UTF_16BE {
+        // only characters outside the BMP take 4 bytes in UTF-8, and they're
+        // the ones that take a surrogate pair in UTF-16
+        Some(|utf_8_len| if utf_8_len == 4 { 4 } else { 2 })
+    } else {
+        None
+    }
+}
+
+struct Transcoder {
+    decoder: encoding_rs::Decoder,
+    text: Vec<u8>,
+    offset_map: OffsetMap,
+    // where the character currently being decoded starts in the original text
+    original_start: usize,
+}
+
+impl Transcoder {
+    /// Decodes `source` (from `original_start`) in one go, working out the
+    /// anchors afterwards from `original_char_len`.
+    fn decode_all(&mut self, source: &[u8], original_char_len: fn(usize) -> usize) {
+        let input = &source[self.original_start..];
+        let max_len = self
+            .decoder
+            .max_utf8_buffer_length(input.len())
+            .expect("the source is already in memory");
+        self.text.resize(max_len, 0);
+        let (_, _, written, _) = self.decoder.decode_to_utf8(input, &mut self.text, true);
+        self.text.truncate(written);
+
+        let mut index = 0;
+        while index < self.text.len() {
+            let len = utf_8_char_len(self.text[index]);
+            let original_len = original_char_len(len);
+            index += len;
+            self.original_start += original_len;
+            if len != original_len {
+                self.offset_map.push(index, self.original_start);
+            }
+        }
+        // eg a trailing odd byte of UTF-16
+        if self.original_start != source.len() {
+            self.offset_map.push(self.text.len(), source.len());
+        }
+    }
+
+    /// Decodes `source` (from `original_start`) a character at a time, so
+    /// that the anchors are known for any encoding, except that runs of ASCII
+    /// are copied over in bulk when `is_ascii_compatible`.
+    fn decode_by_char(&mut self, source: &[u8], is_ascii_compatible: bool) {
+        let mut output = [0; 32];
+        let mut index = self.original_start;
+        while index < source.len() {
+            if is_ascii_compatible && index == self.original_start {
+                let ascii_len = encoding_rs::Encoding::ascii_valid_up_to(&source[index..]);
+                if ascii_len > 0 {
+                    self.text
+                        .extend_from_slice(&source[index..index + ascii_len]);
+                    index += ascii_len;
+                    self.original_start = index;
+                    continue;
+                }
+            }
+            index += 1;
+            self.decode(&source[index - 1..index], false, &mut output, index);
+        }
+        self.decode(&[], true, &mut output, source.len());
+        // eg an ISO-2022-JP escape sequence at the very end
+        if self.original_start != source.len() {
+            self.offset_map.push(self.text.len(), source.len());
+        }
+    }
+
+    fn decode(&mut self, mut input: &[u8], last: bool, output: &mut [u8], original_end: usize) {
+        let transcoded_start = self.text.len();
+        loop {
+            let (result, read, written, _) = self.decoder.decode_to_utf8(input, output, last);
+            self.text.extend_from_slice(&output[..written]);
+            input = &input[read..];
+            if result == CoderResult::InputEmpty {
+                break;
+            }
+        }
+        if self.text.len() == transcoded_start {
+            return;
+        }
+        // A malformed sequence only gets replaced once the byte after it has
+        // been seen, so several characters can come out at once. All but the
+        // last are assumed to take a byte each, keeping the map increasing.
+        let (mut transcoded, mut original) = (transcoded_start, self.original_start);
+        loop {
+            let next = transcoded + utf_8_char_len(self.text[transcoded]);
+            if next == self.text.len() {
+                break;
+            }
+            (transcoded, original) = (next, cmp::min(original + 1, original_end - 1));
+            self.offset_map.push(transcoded, original);
+        }
+        if self.text.len() - transcoded != original_end - original {
+            self.offset_map.push(self.text.len(), original_end);
+        }
+        self.original_start = original_end;
+    }
+}
+
+fn utf_8_char_len(first_byte: u8) -> usize {
+    match first_byte {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        _ => 4,
+    }
+}
@@ -1,19 +1,26 @@
 // derived from https://github.com/BurntSushi/ripgrep/blob/master/crates/searcher/src/searcher/glue.rs
 
-use tree_sitter::{Node, QueryCursor};
+use std::{
+    cell::Ref,
+    iter,
+    time::{Duration, Instant},
+};
+
+use tree_sitter::QueryCursor;
 
 use crate::{
     lines::{self, LineStep},
     query_context::QueryContext,
-    searcher::{core::Core, Config, Range, Searcher},
-    sink::Sink,
-    treesitter::get_parser,
+    searcher::{core::Core, encoding::OffsetMap, Config, Range, Searcher},
+    sink::{MatchedNode, Sink},
+    treesitter::{get_parser, node_sexp},
 };
 
 #[derive(Debug, Default)]
 struct AccumulatedExactMatches {
     matches_with_offsets_relative_to_reference_beginning_of_line_offset: Vec<Range>,
     reference_beginning_of_line_offset: Option<usize>,
+    matched_nodes: Vec<MatchedNode>,
 }
 
 impl AccumulatedExactMatches {
@@ -21,16 +28,14 @@ impl AccumulatedExactMatches {
         self.matches_with_offsets_relative_to_reference_beginning_of_line_offset
             .clear();
         self.reference_beginning_of_line_offset = None;
+        self.matched_nodes.clear();
     }
 
-    pub fn push(
-        &mut self,
-        match_with_absolute_offsets: Range,
-        current_beginning_of_line_offset: usize,
-    ) {
+    pub fn push(&mut self, matched_node: MatchedNode, current_beginning_of_line_offset: usize) {
         if self.reference_beginning_of_line_offset.is_none() {
             self.reference_beginning_of_line_offset = Some(current_beginning_of_line_offset);
         }
+        let match_with_absolute_offsets = matched_node.range();
         self.matches_with_offsets_relative_to_reference_beginning_of_line_offset
             .push(Range::new(
                 match_with_absolute_offsets.start()
@@ -38,6 +43,11 @@ impl AccumulatedExactMatches {
                 match_with_absolute_offsets.end()
                     - self.reference_beginning_of_line_offset.unwrap(),
             ));
+        self.matched_nodes.push(matched_node);
+    }
+
+    pub fn matched_nodes(&self) -> &[MatchedNode] {
+        &self.matched_nodes
     }
 }
 
@@ -52,8 +62,11 @@ pub struct MultiLine<'s, S> {
     config: &'s Config,
     core: Core<'s, S>,
     slice: &'s [u8],
+    offset_map: Ref<'s, OffsetMap>,
     last_match: Option<Range>,
     accumulated_exact_matches: AccumulatedExactMatches,
+    elapsed_parsing: Duration,
+    elapsed_querying: Duration,
 }
 
 impl<'s, S: Sink> MultiLine<'s, S> {
@@ -67,8 +80,11 @@ impl<'s, S: Sink> MultiLine<'s, S> {
             config: &searcher.config,
             core: Core::new(searcher, query_context, write_to),
             slice,
+            offset_map: searcher.offset_map.borrow(),
             last_match: None,
             accumulated_exact_matches: Default::default(),
+            elapsed_parsing: Default::default(),
+            elapsed_querying: Default::default(),
         }
     }
 
@@ -76,34 +92,75 @@ impl<'s, S: Sink> MultiLine<'s, S> {
         if self.core.begin()? {
             let mut keepgoing = true;
             let mut query_cursor = QueryCursor::new();
+            let parse_start_time = Instant::now();
             let tree = get_parser(self.core.query_context().language)
                 .parse(self.slice, None)
                 .unwrap();
+            self.elapsed_parsing = parse_start_time.elapsed();
             let query = self.core.query_context().query.clone();
-            let capture_index = self.core.query_context().capture_index;
+            let capture_indices = self.core.query_context().capture_indices.clone();
             let filter = self.core.query_context().filter.clone();
-            let mut matches = query_cursor
+            let has_quantified_captures = self.core.query_context().has_quantified_captures();
+            let span_quantified_captures = self.config.span_quantified_captures;
+            let captures = self.config.captures;
+            let (sexps, sexp_depth) = (self.config.sexps, self.config.sexp_depth);
+            let offset_map = Ref::clone(&self.offset_map);
+            let matches = query_cursor
                 .captures(&query, tree.root_node(), self.slice)
-                .filter_map(|(match_, found_capture_index)| {
-                    let found_capture_index = found_capture_index as u32;
-                    if found_capture_index != capture_index {
+                .filter_map(|(match_, index_in_match)| {
+                    let capture = &match_.captures[index_in_match];
+                    if !capture_indices.contains(&capture.index) {
                         return None;
                     }
-                    let mut nodes_for_this_capture = match_.nodes_for_capture_index(capture_index);
-                    let single_captured_node = nodes_for_this_capture.next().unwrap();
-                    assert!(
-                        nodes_for_this_capture.next().is_none(),
-                        "I guess .captures() always wraps up the single capture like this?"
-                    );
-                    match filter.as_ref() {
-                        None => Some(single_captured_node),
-                        Some(filter) => filter
-                            .call(&single_captured_node)
-                            .then_some(single_captured_node),
+                    // .captures() yields a match once for each of the nodes
+                    // captured by a quantified capture
+                    let nodes = if span_quantified_captures {
+                        if match_.captures[..index_in_match]
+                            .iter()
+                            .any(|earlier_capture| earlier_capture.index == capture.index)
+                        {
+                            return None;
+                        }
+                        match_
+                            .nodes_for_capture_index(capture.index)
+                            .collect::<Vec<_>>()
+                    } else {
+                        vec![capture.node]
+                    };
+                    if let Some(filter) = filter.as_ref() {
+                        if !nodes.iter().all(|node| filter.call(node)) {
+                            return None;
+                        }
+                    }
+                    let mut matched_node =
+                        MatchedNode::spanning(&nodes[0], nodes.last().unwrap(), capture.index);
+                    if captures {
+                        matched_node = matched_node.with_captures(match_.captures);
+                    }
+                    if !offset_map.is_identity() {
+                        // columns are reported relative to the original text
+                        let range = matched_node.range();
+                        matched_node.start_point.column = offset_map
+                            .original_column(range.start(), matched_node.start_point.column);
+                        matched_node.end_point.column =
+                            offset_map.original_column(range.end(), matched_node.end_point.column);
+                    }
+                    if sexps {
+                        matched_node = matched_node.with_sexps(
+                            nodes
+                                .iter()
+                                .map(|node| node_sexp(node, sexp_depth))
+                                .collect(),
+                        );
                     }
+                    Some((nodes[0].id(), matched_node))
                 });
+            let mut matches = without_redundant_matches(matches, has_quantified_captures);
             while !self.slice[self.core.pos()..].is_empty() && keepgoing {
                 keepgoing = self.sink(&mut matches)?;
+                if self.config.stop_on_first_match && self.last_match.is_some() {
+                    break;
+                }
             }
             if keepgoing {
                 keepgoing = match self.last_match.take() {
@@ -127,36 +184,37 @@ impl<'s, S: Sink> MultiLine<'s, S> {
             }
         }
         let byte_count = self.byte_count();
-        self.core.finish(byte_count)
+        self.core
+            .finish(byte_count, self.elapsed_parsing, self.elapsed_querying)
     }
 
-    fn sink<'tree>(
-        &mut self,
-        matches: &mut impl Iterator<Item = Node<'tree>>,
-    ) -> Result<bool, S::Error> {
+    fn sink(&mut self, matches: &mut impl Iterator<Item = MatchedNode>) -> Result<bool, S::Error> {
         if self.config.invert_match {
             return self.sink_matched_inverted(matches);
         }
-        let mat = match self.find(matches)? {
-            Some(range) => range,
+        let matched_node = match self.find(matches)? {
+            Some(matched_node) => matched_node,
             None => {
                 self.core.set_pos(self.slice.len());
                 return Ok(true);
             }
         };
+        let mat = matched_node.range();
         self.advance(&mat);
 
         let line = lines::locate(self.slice, self.config.line_term.as_byte(), mat);
         match self.last_match.take() {
             None => {
                 self.last_match = Some(line);
-                self.accumulated_exact_matches.push(mat, line.start());
+                self.accumulated_exact_matches
+                    .push(matched_node, line.start());
                 Ok(true)
             }
             Some(last_match) => {
                 if last_match.end() >= line.start() {
                     self.last_match = Some(last_match.with_end_if_extends(line.end()));
-                    self.accumulated_exact_matches.push(mat, line.start());
+                    self.accumulated_exact_matches
+                        .push(matched_node, line.start());
                     Ok(true)
                 } else {
                     self.last_match = Some(line);
@@ -164,16 +222,17 @@ impl<'s, S: Sink> MultiLine<'s, S> {
                         return Ok(false);
                     }
                     let ret = self.sink_matched(&last_match);
-                    self.accumulated_exact_matches.push(mat, line.start());
+                    self.accumulated_exact_matches
+                        .push(matched_node, line.start());
                     ret
                 }
             }
         }
     }
 
-    fn sink_matched_inverted<'tree>(
+    fn sink_matched_inverted(
         &mut self,
-        matches: &mut impl Iterator<Item = Node<'tree>>,
+        matches: &mut impl Iterator<Item = MatchedNode>,
     ) -> Result<bool, S::Error> {
         assert!(self.config.invert_match);
 
@@ -183,8 +242,12 @@ impl<'s, S: Sink> MultiLine<'s, S> {
                 self.core.set_pos(range.end());
                 range
             }
-            Some(mat) => {
-                let line = lines::locate(self.slice, self.config.line_term.as_byte(), mat);
+            Some(matched_node) => {
+                let line = lines::locate(
+                    self.slice,
+                    self.config.line_term.as_byte(),
+                    matched_node.range(),
+                );
                 let range = Range::new(self.core.pos(), line.start());
                 self.advance(&line);
                 range
@@ -213,9 +276,12 @@ impl<'s, S: Sink> MultiLine<'s, S> {
         if range.is_empty() {
             return Ok(false);
         }
-        let ret = self
-            .core
-            .matched(self.slice, range, self.accumulated_exact_matches.as_ref());
+        let ret = self.core.matched(
+            self.slice,
+            range,
+            self.accumulated_exact_matches.as_ref(),
+            self.accumulated_exact_matches.matched_nodes(),
+        );
         self.accumulated_exact_matches.clear();
         ret
     }
@@ -239,11 +305,14 @@ impl<'s, S: Sink> MultiLine<'s, S> {
         Ok(true)
     }
 
-    fn find<'tree>(
+    fn find(
         &mut self,
-        matches: &mut impl Iterator<Item = Node<'tree>>,
-    ) -> Result<Option<Range>, S::Error> {
-        Ok(matches.next().as_ref().map(Into::into))
+        matches: &mut impl Iterator<Item = MatchedNode>,
+    ) -> Result<Option<MatchedNode>, S::Error> {
+        let query_start_time = Instant::now();
+        let matched_node = matches.next();
+        self.elapsed_querying += query_start_time.elapsed();
+        Ok(matched_node)
     }
 
     fn advance(&mut self, range: &Range) {
@@ -258,3 +327,34 @@ impl<'s, S: Sink> MultiLine<'s, S> {
         self.core.pos() as u64
     }
 }
+
+/// Besides the match for a whole run of nodes captured by a quantified capture
+/// (eg `(source_file (use_declaration)+ @uses)`), tree-sitter also reports
+/// matches for the leading parts of that run. Of the consecutive matched
+/// nodes for the same capture that start with the same node (identified by
+/// the accompanying node id), only the longest is kept. Queries without
+/// quantified captures can't produce these, so their matches are passed
+/// through as they are.
+fn without_redundant_matches(
+    matches: impl Iterator<Item = (usize, MatchedNode)>,
+    has_quantified_captures: bool,
+) -> impl Iterator<Item = MatchedNode> {
+    let mut matches = matches.peekable();
+    iter::from_fn(move || {
+        let (first_node_id, mut matched_node) = matches.next()?;
+        if !has_quantified_captures {
+            return Some(matched_node);
+        }
+        while let Some((_, next_matched_node)) =
+            matches.next_if(|(next_first_node_id, next_matched_node)| {
+                *next_first_node_id == first_node_id
+                    && next_matched_node.capture_index == matched_node.capture_index
+            })
+        {
+            if next_matched_node.range().end() > matched_node.range().end() {
+                matched_node = next_matched_node;
+            }
+        }
+        Some(matched_node)
+    })
+}
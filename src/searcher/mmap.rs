@@ -20,12 +20,10 @@ impl Default for MmapChoice {
 }
 
 impl MmapChoice {
-    #[allow(dead_code)]
     pub unsafe fn auto() -> MmapChoice {
         MmapChoice(MmapChoiceImpl::Auto)
     }
 
-    #[allow(dead_code)]
     pub fn never() -> MmapChoice {
         MmapChoice(MmapChoiceImpl::Never)
     }
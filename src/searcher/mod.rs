@@ -1,25 +1,26 @@
 // derived from https://github.com/BurntSushi/ripgrep/blob/master/crates/searcher/src/searcher/mod.rs
 
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     cmp, fmt,
     fs::File,
     io::{self, Read},
     path::Path,
 };
 
-use encoding_rs_io::DecodeReaderBytesBuilder;
-
-pub use self::mmap::MmapChoice;
+use self::encoding::{transcode, OffsetMap, Transcoded};
+pub use self::{encoding::Encoding, mmap::MmapChoice};
 use crate::{
     line_buffer::{alloc_error, DEFAULT_BUFFER_CAPACITY},
     matcher::{LineTerminator, Match},
     query_context::QueryContext,
     searcher::glue::MultiLine,
     sink::{Sink, SinkError},
+    skip::{SkipConfig, SkipReason},
 };
 
 mod core;
+mod encoding;
 mod glue;
 mod mmap;
 
@@ -32,9 +33,17 @@ pub struct Config {
     after_context: usize,
     before_context: usize,
     passthru: bool,
+    stop_on_first_match: bool,
+    span_quantified_captures: bool,
+    captures: bool,
+    sexps: bool,
+    sexp_depth: Option<usize>,
     line_number: bool,
     heap_limit: Option<usize>,
     mmap: MmapChoice,
+    encoding: Option<Encoding>,
+    bom_sniffing: bool,
+    skip: SkipConfig,
 }
 
 impl Default for Config {
@@ -45,9 +54,17 @@ impl Default for Config {
             after_context: 0,
             before_context: 0,
             passthru: false,
+            stop_on_first_match: false,
+            span_quantified_captures: false,
+            captures: false,
+            sexps: false,
+            sexp_depth: None,
             line_number: true,
             heap_limit: None,
             mmap: MmapChoice::default(),
+            encoding: None,
+            bom_sniffing: true,
+            skip: SkipConfig::default(),
         }
     }
 }
@@ -67,6 +84,9 @@ pub enum ConfigError {
         matcher: LineTerminator,
         searcher: LineTerminator,
     },
+    UnknownEncoding {
+        label: String,
+    },
     #[doc(hidden)]
     __Nonexhaustive,
 }
@@ -91,6 +111,9 @@ impl fmt::Display for ConfigError {
                     matcher, searcher
                 )
             }
+            ConfigError::UnknownEncoding { ref label } => {
+                write!(f, "unknown encoding: {}", label)
+            }
             _ => panic!("BUG: unexpected variant found"),
         }
     }
@@ -121,21 +144,15 @@ impl SearcherBuilder {
             config.after_context = 0;
         }
 
-        let mut decode_builder = DecodeReaderBytesBuilder::new();
-        decode_builder
-            .encoding(None)
-            .utf8_passthru(true)
-            .bom_override(true);
-
         Searcher {
             config,
-            decode_builder,
-            decode_buffer: RefCell::new(vec![0; 8 * (1 << 10)]),
             multi_line_buffer: RefCell::new(vec![]),
+            offset_map: Default::default(),
+            transcoded: Cell::new(false),
+            skip_reason: Cell::new(None),
         }
     }
 
-    #[allow(dead_code)]
     pub fn line_terminator(&mut self, line_term: LineTerminator) -> &mut SearcherBuilder {
         self.config.line_term = line_term;
         self
@@ -170,25 +187,79 @@ impl SearcherBuilder {
         self
     }
 
+    pub fn stop_on_first_match(&mut self, yes: bool) -> &mut SearcherBuilder {
+        self.config.stop_on_first_match = yes;
+        self
+    }
+
+    /// Report the run of nodes captured by a quantified capture as a single
+    /// match rather than one match per node.
+    pub fn span_quantified_captures(&mut self, yes: bool) -> &mut SearcherBuilder {
+        self.config.span_quantified_captures = yes;
+        self
+    }
+
+    /// Record all the captures of the query match each matched node came from,
+    /// so that other captures can be looked up by name.
+    pub fn captures(&mut self, yes: bool) -> &mut SearcherBuilder {
+        self.config.captures = yes;
+        self
+    }
+
+    /// Record the S-expression of each matched node, with its descendants
+    /// deeper than `sexp_depth` (if any) left out.
+    pub fn sexps(&mut self, yes: bool) -> &mut SearcherBuilder {
+        self.config.sexps = yes;
+        self
+    }
+
+    pub fn sexp_depth(&mut self, depth: Option<usize>) -> &mut SearcherBuilder {
+        self.config.sexp_depth = depth;
+        self
+    }
+
     #[allow(dead_code)]
     pub fn heap_limit(&mut self, bytes: Option<usize>) -> &mut SearcherBuilder {
         self.config.heap_limit = bytes;
         self
     }
 
-    #[allow(dead_code)]
     pub fn memory_map(&mut self, strategy: MmapChoice) -> &mut SearcherBuilder {
         self.config.mmap = strategy;
         self
     }
+
+    /// Transcode searched text from `encoding` to UTF-8. Byte offsets and
+    /// columns are still reported relative to the original text.
+    ///
+    /// By default, only text that starts with a UTF-16 BOM gets transcoded.
+    pub fn encoding(&mut self, encoding: Option<Encoding>) -> &mut SearcherBuilder {
+        self.config.encoding = encoding;
+        self
+    }
+
+    /// Whether a BOM determines the encoding of searched text, overriding
+    /// any given encoding. Enabled by default.
+    pub fn bom_sniffing(&mut self, yes: bool) -> &mut SearcherBuilder {
+        self.config.bom_sniffing = yes;
+        self
+    }
+
+    /// Which searched files (but not readers or slices) to skip without
+    /// reporting anything to the sink. Nothing is skipped by default.
+    pub fn skip(&mut self, skip: SkipConfig) -> &mut SearcherBuilder {
+        self.config.skip = skip;
+        self
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct Searcher {
     config: Config,
-    decode_builder: DecodeReaderBytesBuilder,
-    decode_buffer: RefCell<Vec<u8>>,
     multi_line_buffer: RefCell<Vec<u8>>,
+    offset_map: RefCell<OffsetMap>,
+    transcoded: Cell<bool>,
+    skip_reason: Cell<Option<SkipReason>>,
 }
 
 impl Searcher {
@@ -235,23 +306,41 @@ impl Searcher {
     where
         S: Sink,
     {
+        self.skip_reason.set(None);
+        if let Ok(metadata) = file.metadata() {
+            if self.record_skip_reason(path, self.config.skip.skip_reason_for_len(metadata.len())) {
+                return Ok(());
+            }
+        }
         if let Some(mmap) = self.config.mmap.open(file, path) {
+            if self.record_skip_reason(path, self.config.skip.skip_reason_for_contents(&mmap)) {
+                return Ok(());
+            }
             log::trace!("{:?}: searching via memory map", path);
             return self.search_slice(query_context, &mmap, write_to);
         }
         log::trace!("{:?}: reading entire file on to heap for mulitline", path);
         self.fill_multi_line_buffer_from_file::<S>(file)?;
+        let skip_reason = self
+            .config
+            .skip
+            .skip_reason_for_contents(&self.multi_line_buffer.borrow());
+        if self.record_skip_reason(path, skip_reason) {
+            return Ok(());
+        }
         log::trace!("{:?}: searching via multiline strategy", path);
-        MultiLine::new(
-            self,
-            query_context,
-            &self.multi_line_buffer.borrow(),
-            write_to,
-        )
-        .run()
+        self.search_multi_line_buffer(query_context, write_to)
+    }
+
+    fn record_skip_reason(&self, path: Option<&Path>, skip_reason: Option<SkipReason>) -> bool {
+        let Some(skip_reason) = skip_reason else {
+            return false;
+        };
+        log::trace!("{:?}: skipping because {}", path, skip_reason);
+        self.skip_reason.set(Some(skip_reason));
+        true
     }
 
-    #[allow(dead_code)]
     pub fn search_reader<R, S>(
         &mut self,
         query_context: QueryContext,
@@ -264,22 +353,11 @@ impl Searcher {
     {
         self.check_config().map_err(S::Error::error_config)?;
 
-        let mut decode_buffer = self.decode_buffer.borrow_mut();
-        let decoder = self
-            .decode_builder
-            .build_with_buffer(read_from, &mut *decode_buffer)
-            .map_err(S::Error::error_io)?;
-
+        self.skip_reason.set(None);
         log::trace!("generic reader: reading everything to heap for multiline");
-        self.fill_multi_line_buffer_from_reader::<_, S>(decoder)?;
+        self.fill_multi_line_buffer_from_reader::<_, S>(read_from)?;
         log::trace!("generic reader: searching via multiline strategy");
-        MultiLine::new(
-            self,
-            query_context,
-            &self.multi_line_buffer.borrow(),
-            write_to,
-        )
-        .run()
+        self.search_multi_line_buffer(query_context, write_to)
     }
 
     pub fn search_slice<S>(
@@ -293,16 +371,47 @@ impl Searcher {
     {
         self.check_config().map_err(S::Error::error_config)?;
 
+        if self.slice_needs_transcoding(slice) {
+            log::trace!("slice reader: needs transcoding, using generic reader");
+            return self.search_reader(query_context, slice, write_to);
+        }
+        self.offset_map.take();
+        self.transcoded.set(false);
+        self.skip_reason.set(None);
         log::trace!("slice reader: searching via multiline strategy");
         MultiLine::new(self, query_context, slice, write_to).run()
     }
 
+    fn search_multi_line_buffer<S: Sink>(
+        &self,
+        query_context: QueryContext,
+        write_to: S,
+    ) -> Result<(), S::Error> {
+        if self.slice_needs_transcoding(&self.multi_line_buffer.borrow()) {
+            self.transcode_multi_line_buffer();
+        } else {
+            self.offset_map.take();
+            self.transcoded.set(false);
+        }
+        MultiLine::new(
+            self,
+            query_context,
+            &self.multi_line_buffer.borrow(),
+            write_to,
+        )
+        .run()
+    }
+
     fn check_config(&self) -> Result<(), ConfigError> {
         if self.config.heap_limit == Some(0) && !self.config.mmap.is_enabled() {
             return Err(ConfigError::SearchUnavailable);
         }
         Ok(())
     }
+
+    fn slice_needs_transcoding(&self, slice: &[u8]) -> bool {
+        self.config.encoding.is_some() || (self.config.bom_sniffing && slice_has_bom(slice))
+    }
 }
 
 impl Searcher {
@@ -328,6 +437,26 @@ impl Searcher {
         self.config.after_context
     }
 
+    /// The byte offset in the original text corresponding to `offset` in the
+    /// searched text, which differ when the original text was transcoded.
+    #[inline]
+    pub fn original_byte_offset(&self, offset: u64) -> u64 {
+        self.offset_map.borrow().original(offset)
+    }
+
+    /// Why the last searched file was skipped rather than searched, if it was.
+    #[inline]
+    pub fn skip_reason(&self) -> Option<SkipReason> {
+        self.skip_reason.get()
+    }
+
+    /// Whether the searched text was transcoded from an encoding other than
+    /// UTF-8, rather than being the original text (apart from any UTF-8 BOM).
+    #[inline]
+    pub fn is_transcoded(&self) -> bool {
+        self.transcoded.get()
+    }
+
     #[inline]
     #[allow(dead_code)]
     pub fn before_context(&self) -> usize {
@@ -340,24 +469,28 @@ impl Searcher {
         self.config.passthru
     }
 
-    fn fill_multi_line_buffer_from_file<S: Sink>(&self, file: &File) -> Result<(), S::Error> {
-        let mut decode_buffer = self.decode_buffer.borrow_mut();
-        let mut read_from = self
-            .decode_builder
-            .build_with_buffer(file, &mut *decode_buffer)
-            .map_err(S::Error::error_io)?;
-
+    fn fill_multi_line_buffer_from_file<S: Sink>(&self, mut file: &File) -> Result<(), S::Error> {
         if self.config.heap_limit.is_none() {
             let mut buf = self.multi_line_buffer.borrow_mut();
             buf.clear();
             let cap = file.metadata().map(|m| m.len() as usize + 1).unwrap_or(0);
             buf.reserve(cap);
-            read_from
-                .read_to_end(&mut buf)
-                .map_err(S::Error::error_io)?;
+            file.read_to_end(&mut buf).map_err(S::Error::error_io)?;
             return Ok(());
         }
-        self.fill_multi_line_buffer_from_reader::<_, S>(read_from)
+        self.fill_multi_line_buffer_from_reader::<_, S>(file)
+    }
+
+    fn transcode_multi_line_buffer(&self) {
+        let mut buf = self.multi_line_buffer.borrow_mut();
+        let Transcoded {
+            text,
+            offset_map,
+            is_transcoded,
+        } = transcode(&buf, self.config.encoding, self.config.bom_sniffing);
+        *buf = text;
+        *self.offset_map.borrow_mut() = offset_map;
+        self.transcoded.set(is_transcoded);
     }
 
     fn fill_multi_line_buffer_from_reader<R: io::Read, S: Sink>(
@@ -408,3 +541,10 @@ impl Searcher {
         }
     }
 }
+
+/// Returns true if and only if the given slice begins with a UTF-8 or UTF-16
+/// BOM, in which case it has to be transcoded rather than being searched
+/// directly.
+pub(crate) fn slice_has_bom(slice: &[u8]) -> bool {
+    encoding_rs::Encoding::for_bom(slice).is_some()
+}
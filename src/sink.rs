@@ -1,6 +1,8 @@
 // derived from https://github.com/BurntSushi/ripgrep/blob/master/crates/searcher/src/sink.rs
 
-use std::{error, fmt, io};
+use std::{error, fmt, io, time::Duration};
+
+use tree_sitter::{Node, Point, QueryCapture};
 
 use crate::{
     lines::LineIter,
@@ -135,6 +137,8 @@ impl<S: Sink + ?Sized> Sink for Box<S> {
 #[derive(Clone, Debug)]
 pub struct SinkFinish {
     pub(crate) byte_count: u64,
+    pub(crate) elapsed_parsing: Duration,
+    pub(crate) elapsed_querying: Duration,
 }
 
 impl SinkFinish {
@@ -142,6 +146,16 @@ impl SinkFinish {
     pub fn byte_count(&self) -> u64 {
         self.byte_count
     }
+
+    #[inline]
+    pub fn elapsed_parsing(&self) -> Duration {
+        self.elapsed_parsing
+    }
+
+    #[inline]
+    pub fn elapsed_querying(&self) -> Duration {
+        self.elapsed_querying
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -153,6 +167,8 @@ pub struct SinkMatch<'b> {
     pub(crate) buffer: &'b [u8],
     pub(crate) bytes_range_in_buffer: std::ops::Range<usize>,
     pub(crate) exact_matches: &'b [Match],
+    pub(crate) matched_nodes: &'b [MatchedNode],
+    pub(crate) capture_names: &'b [String],
 }
 
 impl<'b> SinkMatch<'b> {
@@ -187,6 +203,137 @@ impl<'b> SinkMatch<'b> {
     pub fn bytes_range_in_buffer(&self) -> std::ops::Range<usize> {
         self.bytes_range_in_buffer.clone()
     }
+
+    #[inline]
+    pub fn matched_nodes(&self) -> &'b [MatchedNode] {
+        self.matched_nodes
+    }
+
+    #[inline]
+    pub fn capture_name(&self, matched_node: &MatchedNode) -> &'b str {
+        &self.capture_names[matched_node.capture_index as usize]
+    }
+
+    /// The first node captured as `capture_name` by the same query match as
+    /// `matched_node`, if any.
+    pub fn capture_range(&self, matched_node: &MatchedNode, capture_name: &str) -> Option<Match> {
+        matched_node
+            .captures()
+            .iter()
+            .find(|capture| self.capture_names[capture.capture_index as usize] == capture_name)
+            .map(|capture| capture.range)
+    }
+
+    /// This match cut down to its first `len` matched nodes, with `bytes`
+    /// ending after the last line any of those nodes touch.
+    pub(crate) fn truncated(&self, len: usize) -> SinkMatch<'b> {
+        let matched_nodes = &self.matched_nodes[..len];
+        let start = self.bytes_range_in_buffer.start;
+        let end = self.bytes_range_in_buffer.end;
+        let last_byte = matched_nodes
+            .iter()
+            .map(|matched_node| matched_node.range().end().saturating_sub(1))
+            .max()
+            .unwrap_or(start)
+            .clamp(start, end);
+        let end = memchr::memchr(self.line_term.as_byte(), &self.buffer[last_byte..end])
+            .map_or(end, |index| last_byte + index + 1);
+        SinkMatch {
+            bytes: &self.buffer[start..end],
+            bytes_range_in_buffer: start..end,
+            exact_matches: &self.exact_matches[..len],
+            matched_nodes,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchedNode {
+    pub(crate) range: Match,
+    pub(crate) kind: &'static str,
+    pub(crate) capture_index: u32,
+    pub(crate) start_point: Point,
+    pub(crate) end_point: Point,
+    pub(crate) captures: Vec<MatchedCapture>,
+    pub(crate) sexps: Vec<String>,
+}
+
+impl MatchedNode {
+    pub fn new(node: &Node, capture_index: u32) -> Self {
+        Self::spanning(node, node, capture_index)
+    }
+
+    /// A single match spanning from `first` to `last`, eg for the run of
+    /// nodes captured by a quantified capture.
+    pub fn spanning(first: &Node, last: &Node, capture_index: u32) -> Self {
+        Self {
+            range: Match::new(first.start_byte(), last.end_byte()),
+            kind: first.kind(),
+            capture_index,
+            start_point: first.start_position(),
+            end_point: last.end_position(),
+            captures: vec![],
+            sexps: vec![],
+        }
+    }
+
+    pub fn with_captures(self, captures: &[QueryCapture]) -> Self {
+        Self {
+            captures: captures
+                .iter()
+                .map(|capture| MatchedCapture {
+                    capture_index: capture.index,
+                    range: (&capture.node).into(),
+                })
+                .collect(),
+            ..self
+        }
+    }
+
+    pub fn with_sexps(self, sexps: Vec<String>) -> Self {
+        Self { sexps, ..self }
+    }
+
+    #[inline]
+    pub fn range(&self) -> Match {
+        self.range
+    }
+
+    #[inline]
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    #[inline]
+    pub fn start_point(&self) -> Point {
+        self.start_point
+    }
+
+    #[inline]
+    pub fn end_point(&self) -> Point {
+        self.end_point
+    }
+
+    /// Every capture (including this node's own) of the query match this node
+    /// was captured by, when requested (see `SearcherBuilder::captures`).
+    #[inline]
+    pub fn captures(&self) -> &[MatchedCapture] {
+        &self.captures
+    }
+
+    /// The S-expression of each node this match spans, when requested (see
+    /// `SearcherBuilder::sexps`).
+    #[inline]
+    pub fn sexps(&self) -> &[String] {
+        &self.sexps
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchedCapture {
+    pub(crate) capture_index: u32,
+    pub(crate) range: Match,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -0,0 +1,121 @@
+use std::{error, fmt, str::FromStr};
+
+use crate::{line_buffer::DEFAULT_BUFFER_CAPACITY, searcher::slice_has_bom};
+
+// how much of the start of a file is looked at to tell whether it's binary or
+// minified
+const SNIFF_LEN: usize = DEFAULT_BUFFER_CAPACITY;
+
+// hand-written source code averages well under this many bytes per line, so
+// only files that put most of their code on a few very long lines reach it
+const MINIFIED_MIN_AVERAGE_LINE_LENGTH: usize = 300;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FileSizeError {
+    Invalid(String),
+}
+
+impl error::Error for FileSizeError {}
+
+impl fmt::Display for FileSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            FileSizeError::Invalid(ref original) => write!(
+                f,
+                "invalid file size '{}'. Expected eg '2048', '500K', '10M' or '1G'.",
+                original,
+            ),
+        }
+    }
+}
+
+/// A parsed --max-filesize, eg `10M`: a number of bytes, optionally with a
+/// K, M or G suffix.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FileSize(u64);
+
+impl FromStr for FileSize {
+    type Err = FileSizeError;
+
+    fn from_str(s: &str) -> Result<FileSize, FileSizeError> {
+        let invalid = || FileSizeError::Invalid(s.to_owned());
+        let (number, multiplier) = if let Some(number) = s.strip_suffix('K') {
+            (number, 1 << 10)
+        } else if let Some(number) = s.strip_suffix('M') {
+            (number, 1 << 20)
+        } else if let Some(number) = s.strip_suffix('G') {
+            (number, 1 << 30)
+        } else {
+            (s, 1)
+        };
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        number
+            .checked_mul(multiplier)
+            .map(FileSize)
+            .ok_or_else(invalid)
+    }
+}
+
+/// Why a file wasn't searched.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SkipReason {
+    TooLarge { max_filesize: u64 },
+    Binary,
+    Minified,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            SkipReason::TooLarge { max_filesize } => {
+                write!(f, "it is larger than {max_filesize} bytes")
+            }
+            SkipReason::Binary => write!(f, "it looks like a binary file"),
+            SkipReason::Minified => write!(f, "it looks minified"),
+        }
+    }
+}
+
+/// Which files the searcher skips rather than searching.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SkipConfig {
+    pub max_filesize: Option<FileSize>,
+    pub binary: bool,
+    pub minified: bool,
+    /// Whether files starting with a BOM get transcoded (and so aren't
+    /// considered binary for the NUL bytes of eg UTF-16).
+    pub bom_sniffing: bool,
+}
+
+impl SkipConfig {
+    /// Why a file of `len` bytes shouldn't be searched, if it shouldn't.
+    pub fn skip_reason_for_len(&self, len: u64) -> Option<SkipReason> {
+        let FileSize(max_filesize) = self.max_filesize?;
+        (len > max_filesize).then_some(SkipReason::TooLarge { max_filesize })
+    }
+
+    /// Why a file with the (untranscoded) `contents` shouldn't be searched, if
+    /// it shouldn't. Only the start of `contents` is looked at.
+    pub fn skip_reason_for_contents(&self, contents: &[u8]) -> Option<SkipReason> {
+        let sample = &contents[..contents.len().min(SNIFF_LEN)];
+        if self.binary && looks_binary(sample, self.bom_sniffing) {
+            return Some(SkipReason::Binary);
+        }
+        if self.minified && looks_minified(sample) {
+            return Some(SkipReason::Minified);
+        }
+        None
+    }
+}
+
+// like ripgrep's BinaryDetection, except that UTF-16 (which is full of NUL
+// bytes) gets transcoded when it starts with a BOM and BOM sniffing is on
+fn looks_binary(sample: &[u8], bom_sniffing: bool) -> bool {
+    !(bom_sniffing && slice_has_bom(sample)) && memchr::memchr(b'\0', sample).is_some()
+}
+
+fn looks_minified(sample: &[u8]) -> bool {
+    let lines = sample.strip_suffix(b"\n").unwrap_or(sample);
+    let line_count = memchr::memchr_iter(b'\n', lines).count() + 1;
+    sample.len() / line_count > MINIFIED_MIN_AVERAGE_LINE_LENGTH
+}
@@ -1,4 +1,4 @@
-use tree_sitter::{Language, Node, Parser, Query, QueryError};
+use tree_sitter::{Language, Node, Parser, Query, QueryError, TreeCursor};
 
 use crate::matcher::Match;
 
@@ -14,6 +14,50 @@ pub(crate) fn maybe_get_query(source: &str, language: Language) -> Result<Query,
     Query::new(language, source)
 }
 
+/// Like [`Node::to_sexp`] (including field names), but with the descendants
+/// deeper than `max_depth` (if any) elided as "...".
+pub(crate) fn node_sexp(node: &Node, max_depth: Option<usize>) -> String {
+    let mut sexp = String::new();
+    write_node_sexp(&mut node.walk(), max_depth, &mut sexp);
+    sexp
+}
+
+fn write_node_sexp(cursor: &mut TreeCursor, max_depth: Option<usize>, sexp: &mut String) {
+    let node = cursor.node();
+    sexp.push('(');
+    if node.is_missing() {
+        sexp.push_str("MISSING ");
+    }
+    if node.is_named() {
+        sexp.push_str(node.kind());
+    } else {
+        sexp.push_str(&format!("{:?}", node.kind()));
+    }
+    if max_depth == Some(0) {
+        if node.named_child_count() > 0 {
+            sexp.push_str(" ...");
+        }
+    } else if cursor.goto_first_child() {
+        loop {
+            let child = cursor.node();
+            // like `Node::to_sexp()`, only show anonymous nodes when missing
+            if child.is_named() || child.is_missing() {
+                sexp.push(' ');
+                if let Some(field_name) = cursor.field_name() {
+                    sexp.push_str(field_name);
+                    sexp.push_str(": ");
+                }
+                write_node_sexp(cursor, max_depth.map(|max_depth| max_depth - 1), sexp);
+            }
+            if !cursor.goto_next_sibling() {
+                break;
+            }
+        }
+        cursor.goto_parent();
+    }
+    sexp.push(')');
+}
+
 impl From<&'_ Node<'_>> for Match {
     fn from(node: &Node) -> Self {
         let range = node.range();
@@ -1,14 +1,269 @@
 use std::{
     cell::{OnceCell, RefCell},
+    io,
+    path::Path,
     ptr,
     rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
 };
 
 use termcolor::{Buffer, BufferWriter};
 
-use crate::{printer::Standard, Args};
+use crate::{
+    language::SupportedLanguage,
+    printer::{
+        Json, NodeText, PendingRewrite, Rewrite, RewriteFailure, Sarif, SarifResult, Standard,
+        Stats, Summary, Template,
+    },
+    query_context::QueryContext,
+    searcher::Searcher,
+    sink::{Sink, SinkContext, SinkContextKind, SinkFinish, SinkMatch},
+    Args, Input, NonFatalError,
+};
+
+pub enum Printer {
+    Standard(Standard<Buffer>),
+    Json(Json<Buffer>),
+    Summary(Summary<Buffer>),
+    NodeText(NodeText<Buffer>),
+    Sarif(Sarif<Buffer>),
+    Template(Template<Buffer>),
+    Rewrite(Rewrite<Buffer>),
+}
+
+#[derive(Default)]
+pub struct SearchResult {
+    pub has_match: bool,
+    pub stats: Option<Stats>,
+    pub sarif_results: Vec<SarifResult>,
+    pub non_fatal_error: Option<NonFatalError>,
+    pub pending_rewrite: Option<PendingRewrite>,
+}
+
+impl Printer {
+    pub fn get_mut(&mut self) -> &mut Buffer {
+        match self {
+            Self::Standard(printer) => printer.get_mut(),
+            Self::Json(printer) => printer.get_mut(),
+            Self::Summary(printer) => printer.get_mut(),
+            Self::NodeText(printer) => printer.get_mut(),
+            Self::Sarif(printer) => printer.get_mut(),
+            Self::Template(printer) => printer.get_mut(),
+            Self::Rewrite(printer) => printer.get_mut(),
+        }
+    }
+
+    pub fn search_path(
+        &mut self,
+        searcher: &mut Searcher,
+        query_context: QueryContext,
+        path: &Path,
+        input: Input,
+        language: SupportedLanguage,
+        total_match_budget: Option<&MatchBudget>,
+    ) -> io::Result<SearchResult> {
+        let search = PathSearch {
+            searcher,
+            query_context,
+            path,
+            input,
+            total_match_budget,
+        };
+        match self {
+            Self::Standard(printer) => {
+                let sink = search.run(printer.sink_with_path(path))?;
+                Ok(SearchResult {
+                    has_match: sink.has_match(),
+                    stats: sink.stats().cloned(),
+                    ..Default::default()
+                })
+            }
+            Self::Json(printer) => {
+                let sink = search.run(printer.sink_with_path(path, language))?;
+                Ok(SearchResult {
+                    has_match: sink.has_match(),
+                    stats: Some(sink.stats().clone()),
+                    ..Default::default()
+                })
+            }
+            Self::Summary(printer) => {
+                let sink = search.run(printer.sink_with_path(path))?;
+                Ok(SearchResult {
+                    has_match: sink.has_match(),
+                    stats: sink.stats().cloned(),
+                    ..Default::default()
+                })
+            }
+            Self::NodeText(printer) => {
+                let sink = search.run(printer.sink_with_path(path))?;
+                Ok(SearchResult {
+                    has_match: sink.has_match(),
+                    stats: sink.stats().cloned(),
+                    ..Default::default()
+                })
+            }
+            Self::Sarif(printer) => {
+                let sink = search.run(printer.sink_with_path(path))?;
+                Ok(SearchResult {
+                    has_match: sink.has_match(),
+                    sarif_results: sink.into_results(),
+                    ..Default::default()
+                })
+            }
+            Self::Template(printer) => {
+                let sink = search.run(printer.sink_with_path(path, language))?;
+                Ok(SearchResult {
+                    has_match: sink.has_match(),
+                    stats: sink.stats().cloned(),
+                    ..Default::default()
+                })
+            }
+            Self::Rewrite(printer) => {
+                let sink = search.run(printer.sink_with_path(path))?;
+                let has_match = sink.has_match();
+                let non_fatal_error = sink
+                    .failure()
+                    .map(|failure| rewrite_failure_error(path, failure));
+                Ok(SearchResult {
+                    has_match,
+                    non_fatal_error,
+                    pending_rewrite: sink.into_pending_rewrite(),
+                    ..Default::default()
+                })
+            }
+        }
+    }
+}
+
+/// The parts of a search that are the same whichever printer's sink the
+/// matches end up in.
+struct PathSearch<'a> {
+    searcher: &'a mut Searcher,
+    query_context: QueryContext,
+    path: &'a Path,
+    input: Input,
+    total_match_budget: Option<&'a MatchBudget>,
+}
+
+impl<'a> PathSearch<'a> {
+    fn run<S: Sink>(self, mut sink: S) -> Result<S, S::Error> {
+        let write_to = BudgetedSink::new(&mut sink, self.total_match_budget);
+        match self.input {
+            Input::Path => self
+                .searcher
+                .search_path(self.query_context, self.path, write_to)?,
+            Input::Stdin => {
+                self.searcher
+                    .search_reader(self.query_context, io::stdin().lock(), write_to)?
+            }
+        }
+        Ok(sink)
+    }
+}
+
+pub(crate) fn rewrite_failure_error(path: &Path, failure: &RewriteFailure) -> NonFatalError {
+    match failure {
+        RewriteFailure::Overlap { first, second } => NonFatalError::OverlappingRewrites {
+            path: path.to_owned(),
+            first_line: first.row + 1,
+            first_column: first.column + 1,
+            second_line: second.row + 1,
+            second_column: second.column + 1,
+        },
+        RewriteFailure::Transcoded => NonFatalError::RewriteTranscodedFile {
+            path: path.to_owned(),
+        },
+        RewriteFailure::Modified => NonFatalError::RewriteModifiedFile {
+            path: path.to_owned(),
+        },
+        RewriteFailure::Write(error) => NonFatalError::RewriteWriteError {
+            path: path.to_owned(),
+            message: error.to_string(),
+        },
+    }
+}
+
+pub struct MatchBudget(AtomicU64);
 
-pub type Printer = Standard<Buffer>;
+impl MatchBudget {
+    pub fn new(limit: u64) -> Self {
+        Self(AtomicU64::new(limit))
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.0.load(Ordering::SeqCst) == 0
+    }
+
+    /// Takes up to `wanted` matches from the budget, returning how many were
+    /// actually taken.
+    fn take(&self, wanted: usize) -> usize {
+        let wanted = wanted as u64;
+        match self
+            .0
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |remaining| {
+                (remaining > 0).then(|| remaining.saturating_sub(wanted))
+            }) {
+            Ok(remaining) => remaining.min(wanted) as usize,
+            Err(_) => 0,
+        }
+    }
+}
+
+struct BudgetedSink<'b, S> {
+    sink: S,
+    budget: Option<&'b MatchBudget>,
+}
+
+impl<'b, S: Sink> BudgetedSink<'b, S> {
+    fn new(sink: S, budget: Option<&'b MatchBudget>) -> Self {
+        Self { sink, budget }
+    }
+}
+
+impl<'b, S: Sink> Sink for BudgetedSink<'b, S> {
+    type Error = S::Error;
+
+    fn matched(&mut self, searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, S::Error> {
+        let Some(budget) = self.budget else {
+            return self.sink.matched(searcher, mat);
+        };
+        // Inverted matches have no matched nodes but still count as one.
+        let wanted = mat.matched_nodes().len().max(1);
+        match budget.take(wanted) {
+            0 => Ok(false),
+            taken if taken < wanted => {
+                self.sink.matched(searcher, &mat.truncated(taken))?;
+                Ok(false)
+            }
+            _ => self.sink.matched(searcher, mat),
+        }
+    }
+
+    fn context(
+        &mut self,
+        searcher: &Searcher,
+        context: &SinkContext<'_>,
+    ) -> Result<bool, S::Error> {
+        if context.kind() == &SinkContextKind::Before
+            && self.budget.is_some_and(MatchBudget::is_exhausted)
+        {
+            return Ok(false);
+        }
+        self.sink.context(searcher, context)
+    }
+
+    fn context_break(&mut self, searcher: &Searcher) -> Result<bool, S::Error> {
+        self.sink.context_break(searcher)
+    }
+
+    fn begin(&mut self, searcher: &Searcher) -> Result<bool, S::Error> {
+        self.sink.begin(searcher)
+    }
+
+    fn finish(&mut self, searcher: &Searcher, sink_finish: &SinkFinish) -> Result<(), S::Error> {
+        self.sink.finish(searcher, sink_finish)
+    }
+}
 
 thread_local! {
     static PRINTER: OnceCell<(Rc<RefCell<Printer>>, *const Args)> = Default::default();
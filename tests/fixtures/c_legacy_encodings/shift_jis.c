@@ -0,0 +1 @@
+/* A */ const char *greeting = "ɂ";
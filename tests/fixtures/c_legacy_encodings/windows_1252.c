@@ -0,0 +1,2 @@
+const char *greeting = "dj vu";
+/*  */ const char *farewell = "adis";
@@ -0,0 +1,15 @@
+namespace Example
+{
+    class Program
+    {
+        static void Main()
+        {
+            Greet("world");
+        }
+
+        static void Greet(string name)
+        {
+            System.Console.WriteLine(name);
+        }
+    }
+}
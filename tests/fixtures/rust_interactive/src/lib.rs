@@ -0,0 +1,7 @@
+fn first() {}
+
+fn second() {}
+
+fn third() {}
+
+fn fourth() {}
@@ -0,0 +1,9 @@
+pub const NUMBERS: [u32; 40] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40];
+
+pub fn short() {}
+
+pub fn long() -> u32 { let values = [1000, 2000, 3000, 4000, 5000, 6000, 7000, 8000, 9000]; values.iter().sum::<u32>() + helper(values.len() as u32) + helper(1) }
+
+fn helper(value: u32) -> u32 {
+    value
+}
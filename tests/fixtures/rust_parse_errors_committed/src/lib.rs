@@ -0,0 +1,7 @@
+fn already_broken() {
+    let x = ;
+}
+
+fn fine() -> u32 {
+    1
+}
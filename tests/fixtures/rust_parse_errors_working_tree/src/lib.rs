@@ -0,0 +1,14 @@
+// The existing error moves down a line, which doesn't make it new.
+fn already_broken() {
+    let x = ;
+}
+
+fn fine() -> u32 {
+    1
+}
+
+fn newly_broken() {
+    let y = 2
+    let z = (3;
+    let w = ;
+}
@@ -0,0 +1,3 @@
+fn unchanged() {
+    let = 1;
+}
@@ -0,0 +1 @@
+fn stop_it() {}
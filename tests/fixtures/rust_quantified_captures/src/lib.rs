@@ -0,0 +1,13 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+pub fn first(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+use std::sync::Arc;
+
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct Second(Arc<dyn fmt::Debug>);
@@ -0,0 +1 @@
+fn minified() { let v0 = 0; let v1 = 1; let v2 = 2; let v3 = 3; let v4 = 4; let v5 = 5; let v6 = 6; let v7 = 7; let v8 = 8; let v9 = 9; let v10 = 10; let v11 = 11; let v12 = 12; let v13 = 13; let v14 = 14; let v15 = 15; let v16 = 16; let v17 = 17; let v18 = 18; let v19 = 19; let v20 = 20; let v21 = 21; let v22 = 22; let v23 = 23; let v24 = 24; let v25 = 25; let v26 = 26; let v27 = 27; let v28 = 28; let v29 = 29; }
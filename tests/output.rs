@@ -1,8 +1,13 @@
 mod shared;
 
 use shared::{
-    assert_failure_output, assert_non_match_output, assert_sorted_output,
-    assert_sorted_output_with_no_matches_exit_status, build_example,
+    assert_failure_output, assert_failure_output_outside_git_repo, assert_interactive_rewrite,
+    assert_interactive_rewrite_of_modified_file, assert_json_document_output,
+    assert_non_match_output, assert_rewrite_failure_output, assert_rewritten_file,
+    assert_sorted_failure_output_in_git_repo, assert_sorted_json_output,
+    assert_sorted_null_separated_output, assert_sorted_output,
+    assert_sorted_output_and_error_output, assert_sorted_output_with_no_matches_exit_status,
+    assert_sorted_stats_output, assert_stdin_output, build_example,
 };
 
 #[test]
@@ -176,9 +181,9 @@ fn test_no_query_or_filter_specified() {
         r#"
             $ tree-sitter-grep --language rust
             error: the following required arguments were not provided:
-              <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>>
+              <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>|--new-parse-errors-since <REV>|--dump-tree>
 
-            Usage: tree-sitter-grep --language <LANGUAGE> <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>> [PATHS]...
+            Usage: tree-sitter-grep --language <LANGUAGE> <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>|--new-parse-errors-since <REV>|--dump-tree> [PATHS]...
 
             For more information, try '--help'.
         "#,
@@ -273,6 +278,391 @@ fn test_capture_name() {
     );
 }
 
+#[test]
+fn test_multiple_capture_names() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(call_expression function: (_) @function arguments: (_) @arguments)' --language rust --vimgrep -c function -c arguments
+            src/lib.rs:13:22:@function:        let result = add(2, 2);
+            src/lib.rs:13:25:@arguments:        let result = add(2, 2);
+        "#,
+    );
+}
+
+#[test]
+fn test_all_captures() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name parameters: (parameters) @parameters)' --language rust --vimgrep --all-captures
+            src/helpers.rs:1:8:@name:pub fn helper() {}
+            src/helpers.rs:1:14:@parameters:pub fn helper() {}
+            src/lib.rs:3:8:@name:pub fn add(left: usize, right: usize) -> usize {
+            src/lib.rs:3:11:@parameters:pub fn add(left: usize, right: usize) -> usize {
+            src/lib.rs:12:8:@name:    fn it_works() {
+            src/lib.rs:12:16:@parameters:    fn it_works() {
+            src/stop.rs:1:4:@name:fn stop_it() {}
+            src/stop.rs:1:11:@parameters:fn stop_it() {}
+        "#,
+    );
+}
+
+#[test]
+fn test_quantified_capture() {
+    assert_sorted_output(
+        "rust_quantified_captures",
+        r#"
+            $ tree-sitter-grep -q '(use_declaration)+ @uses' --language rust --vimgrep
+            src/lib.rs:1:1:use std::fmt;
+            src/lib.rs:2:1:use std::io;
+            src/lib.rs:3:1:use std::path::Path;
+            src/lib.rs:9:1:use std::sync::Arc;
+        "#,
+    );
+}
+
+#[test]
+fn test_span_quantified_captures() {
+    assert_sorted_output(
+        "rust_quantified_captures",
+        r#"
+            $ tree-sitter-grep -q '(use_declaration)+ @uses' --language rust --vimgrep --span-quantified-captures
+            src/lib.rs:1:1:use std::fmt;
+            src/lib.rs:9:1:use std::sync::Arc;
+        "#,
+    );
+}
+
+#[test]
+fn test_print_sexp() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(call_expression) @call_expression' --language rust --print-sexp
+            src/lib.rs:13:        let result = add(2, 2);
+            (call_expression function: (identifier) arguments: (arguments (integer_literal) (integer_literal)))
+        "#,
+    );
+}
+
+#[test]
+fn test_print_sexp_depth() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(call_expression) @call_expression' --language rust --vimgrep --print-sexp --sexp-depth 1
+            src/lib.rs:13:22:        let result = add(2, 2);
+            (call_expression function: (identifier) arguments: (arguments ...))
+        "#,
+    );
+}
+
+#[test]
+fn test_dump_tree() {
+    assert_non_match_output(
+        "rust_quantified_captures",
+        r#"
+            $ tree-sitter-grep --dump-tree --range 9-9 src/lib.rs
+            src/lib.rs
+              source_file [0..208] 1:1-14:1
+                use_declaration [110..129] 9:1-9:20
+                  "use" [110..113] 9:1-9:4
+                  argument: scoped_identifier [114..128] 9:5-9:19
+                    path: scoped_identifier [114..123] 9:5-9:14
+                      path: identifier [114..117] 9:5-9:8
+                      "::" [117..119] 9:8-9:10
+                      name: identifier [119..123] 9:10-9:14
+                    "::" [123..125] 9:14-9:16
+                    name: identifier [125..128] 9:16-9:19
+                  ";" [128..129] 9:19-9:20
+        "#,
+    );
+}
+
+#[test]
+fn test_node_text() {
+    assert_non_match_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' --language rust --node-text src/lib.rs
+            src/lib.rs:3:1:
+            pub fn add(left: usize, right: usize) -> usize {
+                left + right
+            }
+            --
+            src/lib.rs:12:5:
+            fn it_works() {
+                    let result = add(2, 2);
+                    assert_eq!(result, 4);
+                }
+        "#,
+    );
+}
+
+#[test]
+fn test_stdin() {
+    assert_stdin_output(
+        "rust_project",
+        "fn foo() {}\n\nfn bar() {}\n",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' --language rust -
+            <stdin>:1:fn foo() {}
+            <stdin>:3:fn bar() {}
+        "#,
+    );
+}
+
+#[test]
+fn test_stdin_filename() {
+    assert_stdin_output(
+        "rust_project",
+        "fn foo() {}\n\nfn bar() {}\n",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' --stdin-filename src/generated.rs
+            src/generated.rs:1:fn foo() {}
+            src/generated.rs:3:fn bar() {}
+        "#,
+    );
+}
+
+#[test]
+fn test_stdin_without_language() {
+    assert_failure_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' -
+            error: searching standard input requires --language or --stdin-filename
+        "#,
+    );
+}
+
+#[test]
+fn test_mmap() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name (#eq? @name "add")) @function_item' --language rust --capture function_item --mmap
+            src/lib.rs:3:pub fn add(left: usize, right: usize) -> usize {
+            src/lib.rs:4:    left + right
+            src/lib.rs:5:}
+        "#,
+    );
+}
+
+#[test]
+fn test_no_mmap() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name (#eq? @name "add")) @function_item' --language rust --capture function_item --no-mmap src/lib.rs
+            src/lib.rs:3:pub fn add(left: usize, right: usize) -> usize {
+            src/lib.rs:4:    left + right
+            src/lib.rs:5:}
+        "#,
+    );
+}
+
+#[test]
+fn test_skip_binary_files() {
+    assert_sorted_output(
+        "rust_skipped_files",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' --files-with-matches
+            src/minified.rs
+            src/normal.rs
+        "#,
+    );
+}
+
+#[test]
+fn test_text() {
+    assert_sorted_output(
+        "rust_skipped_files",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' --files-with-matches --text
+            src/binary.rs
+            src/minified.rs
+            src/normal.rs
+        "#,
+    );
+}
+
+#[test]
+fn test_skip_minified() {
+    assert_sorted_output(
+        "rust_skipped_files",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' --files-with-matches --skip-minified
+            src/normal.rs
+        "#,
+    );
+}
+
+#[test]
+fn test_max_filesize() {
+    assert_sorted_output(
+        "rust_skipped_files",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' --files-with-matches --text --max-filesize 20
+            src/normal.rs
+        "#,
+    );
+}
+
+#[test]
+fn test_max_filesize_invalid() {
+    assert_failure_output(
+        "rust_skipped_files",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' --max-filesize 20X
+            error: invalid value '20X' for '--max-filesize <SIZE>': invalid file size '20X'. Expected eg '2048', '500K', '10M' or '1G'.
+
+            For more information, try '--help'.
+        "#,
+    );
+}
+
+#[test]
+fn test_verbose_skipped_files() {
+    assert_sorted_output_and_error_output(
+        "rust_skipped_files",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' --files-with-matches --skip-minified --max-filesize 1K --verbose
+            src/normal.rs
+        "#,
+        r#"File "src/binary.rs" was skipped because it looks like a binary file
+File "src/minified.rs" was skipped because it looks minified
+"#,
+    );
+}
+
+#[test]
+fn test_skip_binary_files_with_bom_when_not_sniffing_bom() {
+    assert_failure_output(
+        "rust_bom",
+        r#"
+            $ tree-sitter-grep -q '(call_expression) @call' -l rust -E none src/utf_16.rs
+            No files were searched
+        "#,
+    );
+}
+
+#[test]
+fn test_no_files_searched_all_skipped() {
+    assert_failure_output(
+        "rust_skipped_files",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' src/binary.rs
+            No files were searched
+        "#,
+    );
+}
+
+#[test]
+fn test_query_not_parseable_for_any_candidate_language() {
+    assert_failure_output(
+        "c_project",
+        r#"
+            $ tree-sitter-grep -q '(function_itemz) @f'
+            error: couldn't parse query for C, Cpp, or ObjectiveC
+        "#,
+    );
+}
+
+#[test]
+fn test_encoding() {
+    assert_sorted_output(
+        "c_legacy_encodings",
+        r#"
+            $ tree-sitter-grep -q '(string_literal) @string' --vimgrep --encoding windows-1252 windows_1252.c
+            windows_1252.c:1:24:const char *greeting = "déjà vu";
+            windows_1252.c:2:32:/* é */ const char *farewell = "adiós";
+        "#,
+    );
+}
+
+#[test]
+fn test_encoding_byte_offset() {
+    assert_sorted_output(
+        "c_legacy_encodings",
+        r#"
+            $ tree-sitter-grep -q '(string_literal) @string' -o -b -E windows-1252 windows_1252.c
+            windows_1252.c:1:23:"déjà vu"
+            windows_1252.c:2:65:"adiós"
+        "#,
+    );
+}
+
+#[test]
+fn test_encoding_multi_byte() {
+    assert_sorted_output(
+        "c_legacy_encodings",
+        r#"
+            $ tree-sitter-grep -q '(string_literal) @string' --vimgrep -E shift_jis shift_jis.c
+            shift_jis.c:1:35:/* 挨拶 */ const char *greeting = "こんにちは";
+        "#,
+    );
+}
+
+#[test]
+fn test_encoding_invalid() {
+    assert_failure_output(
+        "c_legacy_encodings",
+        r#"
+            $ tree-sitter-grep -q '(string_literal) @string' --encoding foo
+            error: invalid value 'foo' for '--encoding <ENCODING>': unknown encoding: foo
+
+            For more information, try '--help'.
+        "#,
+    );
+}
+
+#[test]
+fn test_crlf() {
+    assert_sorted_output(
+        "csharp_crlf",
+        r#"
+            $ tree-sitter-grep -q '(method_declaration name: (identifier) @name (#eq? @name "Greet")) @method' --capture method --crlf
+            Program.cs:10:        static void Greet(string name)
+            Program.cs:11:        {
+            Program.cs:12:            System.Console.WriteLine(name);
+            Program.cs:13:        }
+        "#,
+    );
+}
+
+#[test]
+fn test_crlf_only_matching() {
+    assert_sorted_output(
+        "csharp_crlf",
+        r#"
+            $ tree-sitter-grep -q '(method_declaration name: (identifier) @name (#eq? @name "Greet")) @method' --capture method --crlf -o
+            Program.cs:10:static void Greet(string name)
+            Program.cs:11:        {
+            Program.cs:12:            System.Console.WriteLine(name);
+            Program.cs:13:        }
+        "#,
+    );
+}
+
+#[test]
+fn test_crlf_context() {
+    assert_sorted_output(
+        "csharp_crlf",
+        r#"
+            $ tree-sitter-grep -q '(invocation_expression) @call' --crlf --after-context 2
+            Program.cs:7:            Greet("world");
+            Program.cs-8-        }
+            Program.cs-9-
+            --
+            Program.cs:12:            System.Console.WriteLine(name);
+            Program.cs-13-        }
+            Program.cs-14-    }
+        "#,
+    );
+}
+
 #[test]
 fn test_predicate() {
     assert_sorted_output(
@@ -317,7 +707,7 @@ fn test_unknown_option() {
 
               tip: a similar argument exists: '--query'
 
-            Usage: tree-sitter-grep <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>> <PATHS|--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--capture <CAPTURE_NAME>|--language <LANGUAGE>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>|--filter-arg <FILTER_ARG>|--vimgrep|--after-context <NUM>|--before-context <NUM>|--context <NUM>|--only-matching|--byte-offset>
+            Usage: tree-sitter-grep <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>|--new-parse-errors-since <REV>|--dump-tree> <PATHS|--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--capture <CAPTURE_NAME>|--all-captures|--span-quantified-captures|--language <LANGUAGE>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>|--filter-arg <FILTER_ARG>|--vimgrep|--after-context <NUM>|--before-context <NUM>|--context <NUM>|--only-matching|--byte-offset|--json|--color <WHEN>|--colors <COLOR_SPEC>|--heading|--no-heading|--count|--count-matches|--files-with-matches|--files-without-match|--stats|--max-count <NUM>|--max-total <NUM>|--max-columns <NUM>|--max-columns-preview|--null|--print0-matches|--node-text|--format <FORMAT>|--sarif-message <MESSAGE>|--format-template <TEMPLATE>|--replace <TEMPLATE>|--rewrite <TEMPLATE>|--write|--diff|--interactive <TEMPLATE>|--new-parse-errors-since <REV>|--print-sexp|--sexp-depth <NUM>|--dump-tree|--range <L1-L2>|--stdin-filename <PATH>|--mmap|--no-mmap|--max-filesize <SIZE>|--text|--skip-minified|--verbose|--encoding <ENCODING>|--crlf>
 
             For more information, try '--help'.
         "#,
@@ -406,7 +796,7 @@ fn test_query_inline_and_query_file_path() {
             $ tree-sitter-grep --query '(function_item) @function_item' --query-file ./function-item.scm --language rust
             error: the argument '--query <QUERY_TEXT>' cannot be used with '--query-file <PATH_TO_QUERY_FILE>'
 
-            Usage: tree-sitter-grep --language <LANGUAGE> <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>> [PATHS]...
+            Usage: tree-sitter-grep --language <LANGUAGE> <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>|--new-parse-errors-since <REV>|--dump-tree> [PATHS]...
 
             For more information, try '--help'.
         "#,
@@ -419,7 +809,7 @@ fn test_help_option() {
         "rust_project",
         r#"
             $ tree-sitter-grep --help
-            Usage: tree-sitter-grep [OPTIONS] <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>> [PATHS]...
+            Usage: tree-sitter-grep [OPTIONS] <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>|--new-parse-errors-since <REV>|--dump-tree> [PATHS]...
 
             Arguments:
               [PATHS]...
@@ -440,7 +830,21 @@ fn test_help_option() {
                       The name of the tree-sitter query capture (without leading "@") whose matching nodes will
                       be output.
 
-                      By default this is the "first" capture encountered in the query source text.
+                      By default this is the "first" capture encountered in the query source text. This flag may
+                      be provided multiple times to output the matching nodes of each of those captures, in
+                      which case --vimgrep labels each line with the name of its capture.
+
+                  --all-captures
+                      Output the matching nodes of every capture in the query.
+
+                      Like when providing --capture multiple times, --vimgrep labels each line with the name of
+                      its capture.
+
+                  --span-quantified-captures
+                      Output the nodes captured by a quantified capture (eg "(use_declaration)+ @uses") as a
+                      single match spanning all of them.
+
+                      By default each of those nodes is output as a separate match.
 
               -l, --language <LANGUAGE>
                       The target language for matching.
@@ -490,36 +894,281 @@ fn test_help_option() {
 
                       If -o (--only-matching) is specified, print the offset of the matching part itself.
 
-              -h, --help
-                      Print help (see a summary with '-h')
-        "#,
-    );
-}
+                  --json
+                      Print results in a JSON Lines format.
 
-#[test]
-fn test_help_short_option() {
-    assert_non_match_output(
-        "rust_project",
-        r#"
-            $ tree-sitter-grep -h
-            Usage: tree-sitter-grep [OPTIONS] <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>> [PATHS]...
+                      Each matching node is emitted as its own "match" message including its path, language,
+                      node kind, capture name, text and (0-based) start/end byte, row and column, surrounded by
+                      "begin"/"end" messages per file and followed by a final "summary" message.
 
-            Arguments:
-              [PATHS]...
+                  --color <WHEN>
+                      When to use colors in the output.
 
-            Options:
-              -Q, --query-file <PATH_TO_QUERY_FILE>
-                      The path to a tree-sitter query file
-              -q, --query <QUERY_TEXT>
-                      The source text of a tree-sitter query
-              -c, --capture <CAPTURE_NAME>
-                      The name of the tree-sitter query capture (without leading "@") whose matching nodes will
-                      be output
-              -l, --language <LANGUAGE>
-                      The target language for matching [possible values: c, c++, c-sharp, css, dockerfile,
-                      elisp, elm, go, html, java, javascript, json, kotlin, lua, objective-c, python, ruby,
-                      rust, swift, toml, tree-sitter-query, typescript]
-              -f, --filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>
+                      With "auto", colors are only used when printing to a terminal. "ansi" is like "always" but
+                      uses ANSI escape sequences even on Windows.
+
+                      [default: auto]
+                      [possible values: never, auto, always, ansi]
+
+                  --colors <COLOR_SPEC>
+                      Customize the colors used in the output.
+
+                      Specs have the form "{type}:{attribute}:{value}", where {type} is one of path, line,
+                      column or match, {attribute} is one of fg, bg or style and {value} is a color (for fg/bg)
+                      or a style (for style). Eg "path:fg:magenta" or "match:style:bold". "{type}:none" clears
+                      the colors for that type.
+
+                      This flag may be provided multiple times, later specs overriding earlier ones.
+
+                  --heading
+                      Print the file path above clusters of matches from each file instead of at the start of
+                      each matching line, separating the matches from different files by an empty line.
+
+                      This is the default when printing to a terminal. It can be disabled with --no-heading.
+
+                  --no-heading
+                      Don't group matches by each file path, instead printing the file path at the start of each
+                      matching line.
+
+                      This is the default when not printing to a terminal.
+
+                  --count
+                      Only print the number of matching lines for each file, instead of the matches themselves.
+
+                      Lines spanned by a multi-line matching node are each counted. Files with no matches are
+                      not printed.
+
+                  --count-matches
+                      Only print the number of matching nodes for each file, instead of the matches themselves.
+
+                      Unlike --count, each matching node is counted once regardless of how many lines it spans
+                      or how many other matching nodes share its lines.
+
+                  --files-with-matches
+                      Only print the paths of files containing at least one match.
+
+                      Searching a file stops as soon as its first match is found.
+
+                  --files-without-match
+                      Only print the paths of searched files that don't contain any matches.
+
+                      Searching a file stops as soon as its first match is found.
+
+                  --stats
+                      Print aggregate statistics about the search once it's finished.
+
+                      These include the number of matches, files searched and bytes searched, as well as how
+                      much of the time was spent parsing files versus running the query against the parsed
+                      trees.
+
+                      With --json, these statistics are always included in the final "summary" message.
+
+              -m, --max-count <NUM>
+                      Limit the number of matches printed per file to NUM.
+
+                      Matching nodes that share lines count as a single match.
+
+                  --max-total <NUM>
+                      Limit the total number of matches printed across all files to NUM.
+
+                      Unlike with -m/--max-count, each matching node counts as a separate match. Once NUM
+                      matches have been printed, no further files are searched. Which matches get printed is not
+                      deterministic when searching multiple files.
+
+              -M, --max-columns <NUM>
+                      Don't print lines longer than NUM bytes.
+
+                      Such lines are replaced by a message indicating that they were omitted, unless
+                      --max-columns-preview is also given.
+
+                  --max-columns-preview
+                      Print a preview of lines exceeding --max-columns instead of omitting them.
+
+                      The preview is the first NUM graphemes of the line, or a window of NUM graphemes leading
+                      up to and including the first matching node on the line when that node starts further
+                      along.
+
+              -0, --null
+                      Follow each printed file path with a NUL byte instead of the usual newline or ":"
+                      separator.
+
+                      This is useful for piping paths to eg `xargs -0`.
+
+                  --print0-matches
+                      Print the exact text of each matching node followed by a NUL byte, without any file paths,
+                      line numbers or line framing.
+
+                      Matching nodes spanning multiple lines are printed intact, which makes this suitable for
+                      piping to eg `xargs -0`.
+
+                  --node-text
+                      Print the exact text of each matching node, preceded by a "path:line:column:" header line.
+
+                      Unlike the default line-oriented output, matching nodes are neither widened to whole lines
+                      nor clipped to the matching lines. Consecutive nodes are separated by a "--" line.
+
+                  --format <FORMAT>
+                      Print results in an alternative output format.
+
+                      With "sarif", a single SARIF 2.1.0 log containing a result for each matching node is
+                      printed once every file has been searched. Each result's rule id is the name of the query
+                      file (without extension) if --query-file was given, otherwise the name of the capture.
+
+                      [possible values: sarif]
+
+                  --sarif-message <MESSAGE>
+                      The message attached to each result when using --format sarif.
+
+                      By default the message names the kind of the matching node.
+
+                  --format-template <TEMPLATE>
+                      Print a line rendered from TEMPLATE for each matching node.
+
+                      Placeholders are {path}, {language}, {kind} (the node kind), {capture} (the capture name),
+                      {text}, {start_line}, {end_line}, {start_column}, {end_column}, {start_byte} and
+                      {end_byte}. Lines and columns are 1-based, bytes are 0-based. {@NAME} is replaced by the
+                      text of the node captured as NAME by the same query match, or by nothing if that capture
+                      didn't match anything. Use "{{" and "}}" for literal braces.
+
+              -r, --replace <TEMPLATE>
+                      Print each matching line with every matching node replaced by TEMPLATE.
+
+                      TEMPLATE may reference the text of other nodes captured by the same query match as $NAME
+                      or ${NAME}, where NAME is the capture name (without leading "@"). Use "$$" for a literal
+                      "$". Files are never modified.
+
+                  --rewrite <TEMPLATE>
+                      Rewrite every matching node to TEMPLATE, which uses the same syntax as -r/--replace.
+
+                      This requires either --write or --diff. Files containing overlapping matching nodes are
+                      not rewritten and reported as errors instead.
+
+                  --write
+                      Apply --rewrite to the matching files in place.
+
+                  --diff
+                      Print the changes --rewrite would make as a unified diff instead of applying them.
+
+                  --interactive <TEMPLATE>
+                      Interactively review rewriting every matching node to TEMPLATE, which uses the same syntax
+                      as -r/--replace.
+
+                      Once every file has been searched, each proposed rewrite is shown as a diff hunk, one file
+                      at a time. Answer "y" to rewrite it, "n" to skip it, "e" to type a different replacement,
+                      "a" to rewrite it and all the remaining ones in the file, or "q" to stop reviewing. The
+                      accepted rewrites of each file are applied in place after its review.
+
+                  --new-parse-errors-since <REV>
+                      Instead of running a query, report the ERROR and MISSING nodes of each searched file that
+                      don't exist in its version at the git revision REV.
+
+                      Each file is parsed as both versions with the same grammar, and the git objects are read
+                      from the local repository. The exit status is 2 when any new parse errors are found.
+
+                  --print-sexp
+                      Print the tree-sitter S-expression of each matching node (including field names) beneath
+                      its match.
+
+                  --sexp-depth <NUM>
+                      Limit the S-expressions printed by --print-sexp to NUM levels of descendants of each
+                      matching node.
+
+                      Deeper descendants are elided as "...".
+
+                  --dump-tree
+                      Instead of running a query, print the syntax tree of each searched file.
+
+                      Every named and anonymous node is shown, along with its field name, byte range and
+                      (1-based) start and end line:column. Files are parsed with the same grammars that are used
+                      for searching.
+
+                  --range <L1-L2>
+                      Only print the nodes of --dump-tree that overlap lines L1 through L2 (inclusive), or just
+                      line L1 when given without "-L2".
+
+                  --stdin-filename <PATH>
+                      Search standard input, reporting it as PATH and inferring its language from PATH's file
+                      type unless --language is given.
+
+                      Standard input is also searched, reported as "<stdin>", when "-" is the only path given.
+                      Either this option or --language is needed to know which language standard input is in.
+
+                  --mmap
+                      Search files through memory maps instead of reading them on to the heap.
+
+                      By default, memory maps are only used when searching a few explicitly given files, since
+                      setting one up costs more than reading a small file does. Files that are being rewritten
+                      are never memory mapped. This can be disabled with --no-mmap.
+
+                  --no-mmap
+                      Never search files through memory maps.
+
+                  --max-filesize <SIZE>
+                      Skip files that are larger than SIZE bytes.
+
+                      SIZE can be given with a K, M or G suffix, eg 500K or 10M.
+
+                  --text
+                      Search files that look binary instead of skipping them.
+
+                      By default, files with a NUL byte near their start are considered binary and skipped.
+
+                  --skip-minified
+                      Skip files that look minified, ie whose lines near their start are very long on average.
+
+                  --verbose
+                      Report each file that was skipped without being searched, because it looked binary or
+                      minified or was larger than --max-filesize.
+
+              -E, --encoding <ENCODING>
+                      Transcode searched files from ENCODING to UTF-8.
+
+                      ENCODING can be any label from the Encoding Standard, eg windows-1252, shift_jis or
+                      utf-16le, or `none` to search the raw bytes of files. Byte offsets and columns are still
+                      reported relative to the original files. Files that start with a BOM are transcoded
+                      according to it, unless ENCODING is `none`.
+
+                  --crlf
+                      Treat CRLF (\r\n) as a line terminator, as well as a plain \n.
+
+                      The \r of each CRLF line terminator is then left out of printed lines and
+                      -o/--only-matching fragments, which are always terminated with a plain \n.
+
+              -h, --help
+                      Print help (see a summary with '-h')
+        "#,
+    );
+}
+
+#[test]
+fn test_help_short_option() {
+    assert_non_match_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -h
+            Usage: tree-sitter-grep [OPTIONS] <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>|--new-parse-errors-since <REV>|--dump-tree> [PATHS]...
+
+            Arguments:
+              [PATHS]...
+
+            Options:
+              -Q, --query-file <PATH_TO_QUERY_FILE>
+                      The path to a tree-sitter query file
+              -q, --query <QUERY_TEXT>
+                      The source text of a tree-sitter query
+              -c, --capture <CAPTURE_NAME>
+                      The name of the tree-sitter query capture (without leading "@") whose matching nodes will
+                      be output
+                  --all-captures
+                      Output the matching nodes of every capture in the query
+                  --span-quantified-captures
+                      Output the nodes captured by a quantified capture (eg "(use_declaration)+ @uses") as a
+                      single match spanning all of them
+              -l, --language <LANGUAGE>
+                      The target language for matching [possible values: c, c++, c-sharp, css, dockerfile,
+                      elisp, elm, go, html, java, javascript, json, kotlin, lua, objective-c, python, ruby,
+                      rust, swift, toml, tree-sitter-query, typescript]
+              -f, --filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>
                       The path to a dynamic library that can be used as a "filter plugin"
               -a, --filter-arg <FILTER_ARG>
                       An arbitrary argument to be passed to the specified filter plugin
@@ -536,6 +1185,96 @@ fn test_help_short_option() {
                       separate output line
               -b, --byte-offset
                       Print the 0-based byte offset within the input file before each line of output
+                  --json
+                      Print results in a JSON Lines format
+                  --color <WHEN>
+                      When to use colors in the output [default: auto] [possible values: never, auto, always,
+                      ansi]
+                  --colors <COLOR_SPEC>
+                      Customize the colors used in the output
+                  --heading
+                      Print the file path above clusters of matches from each file instead of at the start of
+                      each matching line, separating the matches from different files by an empty line
+                  --no-heading
+                      Don't group matches by each file path, instead printing the file path at the start of each
+                      matching line
+                  --count
+                      Only print the number of matching lines for each file, instead of the matches themselves
+                  --count-matches
+                      Only print the number of matching nodes for each file, instead of the matches themselves
+                  --files-with-matches
+                      Only print the paths of files containing at least one match
+                  --files-without-match
+                      Only print the paths of searched files that don't contain any matches
+                  --stats
+                      Print aggregate statistics about the search once it's finished
+              -m, --max-count <NUM>
+                      Limit the number of matches printed per file to NUM
+                  --max-total <NUM>
+                      Limit the total number of matches printed across all files to NUM
+              -M, --max-columns <NUM>
+                      Don't print lines longer than NUM bytes
+                  --max-columns-preview
+                      Print a preview of lines exceeding --max-columns instead of omitting them
+              -0, --null
+                      Follow each printed file path with a NUL byte instead of the usual newline or ":"
+                      separator
+                  --print0-matches
+                      Print the exact text of each matching node followed by a NUL byte, without any file paths,
+                      line numbers or line framing
+                  --node-text
+                      Print the exact text of each matching node, preceded by a "path:line:column:" header line
+                  --format <FORMAT>
+                      Print results in an alternative output format [possible values: sarif]
+                  --sarif-message <MESSAGE>
+                      The message attached to each result when using --format sarif
+                  --format-template <TEMPLATE>
+                      Print a line rendered from TEMPLATE for each matching node
+              -r, --replace <TEMPLATE>
+                      Print each matching line with every matching node replaced by TEMPLATE
+                  --rewrite <TEMPLATE>
+                      Rewrite every matching node to TEMPLATE, which uses the same syntax as -r/--replace
+                  --write
+                      Apply --rewrite to the matching files in place
+                  --diff
+                      Print the changes --rewrite would make as a unified diff instead of applying them
+                  --interactive <TEMPLATE>
+                      Interactively review rewriting every matching node to TEMPLATE, which uses the same syntax
+                      as -r/--replace
+                  --new-parse-errors-since <REV>
+                      Instead of running a query, report the ERROR and MISSING nodes of each searched file that
+                      don't exist in its version at the git revision REV
+                  --print-sexp
+                      Print the tree-sitter S-expression of each matching node (including field names) beneath
+                      its match
+                  --sexp-depth <NUM>
+                      Limit the S-expressions printed by --print-sexp to NUM levels of descendants of each
+                      matching node
+                  --dump-tree
+                      Instead of running a query, print the syntax tree of each searched file
+                  --range <L1-L2>
+                      Only print the nodes of --dump-tree that overlap lines L1 through L2 (inclusive), or just
+                      line L1 when given without "-L2"
+                  --stdin-filename <PATH>
+                      Search standard input, reporting it as PATH and inferring its language from PATH's file
+                      type unless --language is given
+                  --mmap
+                      Search files through memory maps instead of reading them on to the heap
+                  --no-mmap
+                      Never search files through memory maps
+                  --max-filesize <SIZE>
+                      Skip files that are larger than SIZE bytes
+                  --text
+                      Search files that look binary instead of skipping them
+                  --skip-minified
+                      Skip files that look minified, ie whose lines near their start are very long on average
+                  --verbose
+                      Report each file that was skipped without being searched, because it looked binary or
+                      minified or was larger than --max-filesize
+              -E, --encoding <ENCODING>
+                      Transcode searched files from ENCODING to UTF-8
+                  --crlf
+                      Treat CRLF (\r\n) as a line terminator, as well as a plain \n
               -h, --help
                       Print help (see more with '--help')
         "#,
@@ -549,9 +1288,9 @@ fn test_no_arguments() {
         r#"
             $ tree-sitter-grep
             error: the following required arguments were not provided:
-              <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>>
+              <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>|--new-parse-errors-since <REV>|--dump-tree>
 
-            Usage: tree-sitter-grep <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>> [PATHS]...
+            Usage: tree-sitter-grep <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>|--new-parse-errors-since <REV>|--dump-tree> [PATHS]...
 
             For more information, try '--help'.
         "#,
@@ -569,7 +1308,7 @@ fn test_filter_argument_no_filter() {
             error: the following required arguments were not provided:
               --filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>
 
-            Usage: tree-sitter-grep --language <LANGUAGE> --filter-arg <FILTER_ARG> <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>> [PATHS]...
+            Usage: tree-sitter-grep --language <LANGUAGE> --filter-arg <FILTER_ARG> <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>|--new-parse-errors-since <REV>|--dump-tree> [PATHS]...
 
             For more information, try '--help'.
         "#,
@@ -1195,3 +1934,860 @@ fn test_byte_offset_only_matching() {
         "#,
     );
 }
+
+#[test]
+fn test_json() {
+    assert_sorted_json_output(
+        "rust_project_byte_offset",
+        r#"
+            $ tree-sitter-grep -q '(parameter) @p' -l rust --json src/lib.rs
+            {"type":"begin","data":{"path":{"text":"src/lib.rs"},"language":"Rust"}}
+            {"type":"match","data":{"path":{"text":"src/lib.rs"},"language":"Rust","kind":"parameter","capture":"p","text":{"text":"left: usize"},"start":{"byte":25,"row":2,"column":11},"end":{"byte":36,"row":2,"column":22}}}
+            {"type":"match","data":{"path":{"text":"src/lib.rs"},"language":"Rust","kind":"parameter","capture":"p","text":{"text":"right: usize"},"start":{"byte":38,"row":2,"column":24},"end":{"byte":50,"row":2,"column":36}}}
+            {"type":"end","data":{"path":{"text":"src/lib.rs"},"stats":{"searches":1,"searches_with_match":1,"bytes_searched":241,"bytes_printed":502,"matched_lines":1,"matches":2}}}
+            {"type":"summary","data":{"stats":{"searches":1,"searches_with_match":1,"bytes_searched":241,"bytes_printed":502,"matched_lines":1,"matches":2}}}
+        "#,
+    );
+}
+
+#[test]
+fn test_json_context() {
+    assert_sorted_json_output(
+        "rust_project_byte_offset",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --json -A 1 src/lib.rs
+            {"type":"begin","data":{"path":{"text":"src/lib.rs"},"language":"Rust"}}
+            {"type":"match","data":{"path":{"text":"src/lib.rs"},"language":"Rust","kind":"identifier","capture":"name","text":{"text":"add"},"start":{"byte":21,"row":2,"column":7},"end":{"byte":24,"row":2,"column":10}}}
+            {"type":"context","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"    left + right\n"},"line_number":4,"absolute_offset":63}}
+            {"type":"match","data":{"path":{"text":"src/lib.rs"},"language":"Rust","kind":"identifier","capture":"name","text":{"text":"it_works"},"start":{"byte":146,"row":11,"column":7},"end":{"byte":154,"row":11,"column":15}}}
+            {"type":"context","data":{"path":{"text":"src/lib.rs"},"lines":{"text":"        let result = add(2, 2);\n"},"line_number":13,"absolute_offset":159}}
+            {"type":"end","data":{"path":{"text":"src/lib.rs"},"stats":{"searches":1,"searches_with_match":1,"bytes_searched":241,"bytes_printed":781,"matched_lines":2,"matches":2}}}
+            {"type":"summary","data":{"stats":{"searches":1,"searches_with_match":1,"bytes_searched":241,"bytes_printed":781,"matched_lines":2,"matches":2}}}
+        "#,
+    );
+}
+
+#[test]
+fn test_json_multiline_overlapping_matches() {
+    assert_sorted_json_output(
+        "rust_project_byte_offset",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @f' -l rust --json src/lib.rs
+            {"type":"begin","data":{"path":{"text":"src/lib.rs"},"language":"Rust"}}
+            {"type":"match","data":{"path":{"text":"src/lib.rs"},"language":"Rust","kind":"function_item","capture":"f","text":{"text":"pub fn add(left: usize, right: usize) -> usize {\n    left + right\n}"},"start":{"byte":14,"row":2,"column":0},"end":{"byte":81,"row":4,"column":1}}}
+            {"type":"match","data":{"path":{"text":"src/lib.rs"},"language":"Rust","kind":"function_item","capture":"f","text":{"text":"fn it_works() {\n        let result = add(2, 2);\n        assert_eq!(result, 4);\n    }"},"start":{"byte":143,"row":11,"column":4},"end":{"byte":227,"row":14,"column":5}}}
+            {"type":"end","data":{"path":{"text":"src/lib.rs"},"stats":{"searches":1,"searches_with_match":1,"bytes_searched":241,"bytes_printed":643,"matched_lines":7,"matches":2}}}
+            {"type":"summary","data":{"stats":{"searches":1,"searches_with_match":1,"bytes_searched":241,"bytes_printed":643,"matched_lines":7,"matches":2}}}
+        "#,
+    );
+}
+
+#[test]
+fn test_color_always() {
+    assert_sorted_output(
+        "rust_project",
+        "
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --color always --colors 'path:fg:magenta' src/lib.rs
+            \x1b[0m\x1b[35msrc/lib.rs\x1b[0m:\x1b[0m\x1b[32m3\x1b[0m:pub fn \x1b[0m\x1b[1m\x1b[31madd\x1b[0m(left: usize, right: usize) -> usize {
+            \x1b[0m\x1b[35msrc/lib.rs\x1b[0m:\x1b[0m\x1b[32m12\x1b[0m:    fn \x1b[0m\x1b[1m\x1b[31mit_works\x1b[0m() {
+        ",
+    );
+}
+
+#[test]
+fn test_color_never() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --color never src/lib.rs
+            src/lib.rs:3:pub fn add(left: usize, right: usize) -> usize {
+            src/lib.rs:12:    fn it_works() {
+        "#,
+    );
+}
+
+#[test]
+fn test_colors() {
+    assert_sorted_output(
+        "rust_project",
+        "
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --color always --colors 'match:fg:blue' --colors 'path:none' src/lib.rs
+            \x1b[0msrc/lib.rs\x1b[0m:\x1b[0m\x1b[32m3\x1b[0m:pub fn \x1b[0m\x1b[1m\x1b[34madd\x1b[0m(left: usize, right: usize) -> usize {
+            \x1b[0msrc/lib.rs\x1b[0m:\x1b[0m\x1b[32m12\x1b[0m:    fn \x1b[0m\x1b[1m\x1b[34mit_works\x1b[0m() {
+        ",
+    );
+}
+
+#[test]
+fn test_colors_invalid_spec() {
+    assert_failure_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --colors 'match:foo:blue'
+            error: invalid value 'match:foo:blue' for '--colors <COLOR_SPEC>': unrecognized spec type 'foo'. Choose from: fg, bg, style, none.
+
+            For more information, try '--help'.
+        "#,
+    );
+}
+
+#[test]
+fn test_heading() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --heading src/lib.rs src/stop.rs src/helpers.rs
+            src/lib.rs
+            3:pub fn add(left: usize, right: usize) -> usize {
+            12:    fn it_works() {
+
+            src/stop.rs
+            1:fn stop_it() {}
+
+            src/helpers.rs
+            1:pub fn helper() {}
+        "#,
+    );
+}
+
+#[test]
+fn test_no_heading_overrides_heading() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --heading --no-heading
+            src/lib.rs:3:pub fn add(left: usize, right: usize) -> usize {
+            src/lib.rs:12:    fn it_works() {
+            src/stop.rs:1:fn stop_it() {}
+            src/helpers.rs:1:pub fn helper() {}
+        "#,
+    );
+}
+
+#[test]
+fn test_count() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' -l rust --count
+            src/lib.rs:7
+            src/stop.rs:1
+            src/helpers.rs:1
+        "#,
+    );
+}
+
+#[test]
+fn test_count_matches() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' -l rust --count-matches
+            src/lib.rs:2
+            src/stop.rs:1
+            src/helpers.rs:1
+        "#,
+    );
+}
+
+#[test]
+fn test_count_multiple_matches_per_line() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(parameter) @parameter' -l rust --count
+            src/lib.rs:1
+        "#,
+    );
+}
+
+#[test]
+fn test_count_matches_multiple_matches_per_line() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(parameter) @parameter' -l rust --count-matches
+            src/lib.rs:2
+        "#,
+    );
+}
+
+#[test]
+fn test_files_with_matches() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' -l rust --files-with-matches src/lib.rs src/stop.rs src/helpers.rs
+            src/lib.rs
+            src/stop.rs
+            src/helpers.rs
+        "#,
+    );
+}
+
+#[test]
+fn test_files_without_match() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(let_declaration) @let_declaration' -l rust --files-without-match src/lib.rs src/stop.rs src/helpers.rs
+            src/stop.rs
+            src/helpers.rs
+        "#,
+    );
+}
+
+#[test]
+fn test_files_without_match_no_files() {
+    assert_sorted_output_with_no_matches_exit_status(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' -l rust --files-without-match src/lib.rs src/stop.rs src/helpers.rs
+        "#,
+    );
+}
+
+#[test]
+fn test_stats() {
+    assert_sorted_stats_output(
+        "rust_project_byte_offset",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' -l rust --stats src/lib.rs
+            src/lib.rs:3:pub fn add(left: usize, right: usize) -> usize {
+            src/lib.rs:4:    left + right
+            src/lib.rs:5:}
+            src/lib.rs:12:    fn it_works() {
+            src/lib.rs:13:        let result = add(2, 2);
+            src/lib.rs:14:        assert_eq!(result, 4);
+            src/lib.rs:15:    }
+
+            2 matches
+            7 matched lines
+            1 files contained matches
+            1 files searched
+            252 bytes printed
+            241 bytes searched
+            <duration> seconds spent searching
+            <duration> seconds spent parsing
+            <duration> seconds spent querying
+            <duration> seconds
+        "#,
+    );
+}
+
+#[test]
+fn test_stats_files_with_matches() {
+    assert_sorted_stats_output(
+        "rust_project_byte_offset",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' -l rust --stats --files-with-matches src/lib.rs
+            src/lib.rs
+
+            1 matches
+            3 matched lines
+            1 files contained matches
+            1 files searched
+            11 bytes printed
+            81 bytes searched
+            <duration> seconds spent searching
+            <duration> seconds spent parsing
+            <duration> seconds spent querying
+            <duration> seconds
+        "#,
+    );
+}
+
+#[test]
+fn test_max_count() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust -m 1
+            src/lib.rs:3:pub fn add(left: usize, right: usize) -> usize {
+            src/stop.rs:1:fn stop_it() {}
+            src/helpers.rs:1:pub fn helper() {}
+        "#,
+    );
+}
+
+#[test]
+fn test_max_count_count() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --max-count 1 --count
+            src/lib.rs:1
+            src/stop.rs:1
+            src/helpers.rs:1
+        "#,
+    );
+}
+
+#[test]
+fn test_max_total() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --max-total 1 src/lib.rs
+            src/lib.rs:3:pub fn add(left: usize, right: usize) -> usize {
+        "#,
+    );
+}
+
+#[test]
+fn test_max_total_matches_sharing_a_line() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(parameter) @parameter' -l rust --max-total 1 --only-matching src/lib.rs
+            src/lib.rs:3:left: usize
+        "#,
+    );
+}
+
+#[test]
+fn test_max_total_zero() {
+    assert_failure_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --max-total 0
+            error: invalid value '0' for '--max-total <NUM>': 0 is not in 1..18446744073709551615
+
+            For more information, try '--help'.
+        "#,
+    );
+}
+
+#[test]
+fn test_max_columns() {
+    assert_sorted_output(
+        "rust_long_lines",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust -M 60
+            src/lib.rs:3:pub fn short() {}
+            src/lib.rs:5:[Omitted long line with 1 matches]
+            src/lib.rs:7:fn helper(value: u32) -> u32 {
+        "#,
+    );
+}
+
+#[test]
+fn test_max_columns_preview() {
+    assert_sorted_output(
+        "rust_long_lines",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --max-columns 60 --max-columns-preview
+            src/lib.rs:3:pub fn short() {}
+            src/lib.rs:5:pub fn long() -> u32 { let values = [1000, 2000, 3000, 4000, [... 0 more matches]
+            src/lib.rs:7:fn helper(value: u32) -> u32 {
+        "#,
+    );
+}
+
+#[test]
+fn test_max_columns_preview_match_past_end_of_preview() {
+    assert_sorted_output(
+        "rust_long_lines",
+        r#"
+            $ tree-sitter-grep -q '(call_expression function: (identifier) @function_name)' -l rust -M 60 --max-columns-preview
+            src/lib.rs:5:[... omitted start of long line] sum::<u32>() + helper(values.len() as u32) + helper(1) } [... 0 more matches]
+        "#,
+    );
+}
+
+#[test]
+fn test_max_columns_preview_requires_max_columns() {
+    assert_failure_output(
+        "rust_long_lines",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --max-columns-preview
+            error: the following required arguments were not provided:
+              --max-columns <NUM>
+
+            Usage: tree-sitter-grep --max-columns <NUM> --language <LANGUAGE> --max-columns-preview <--query-file <PATH_TO_QUERY_FILE>|--query <QUERY_TEXT>|--filter <PATH_TO_FILTER_PLUGIN_DYNAMIC_LIBRARY>|--new-parse-errors-since <REV>|--dump-tree> [PATHS]...
+
+            For more information, try '--help'.
+        "#,
+    );
+}
+
+#[test]
+fn test_null_files_with_matches() {
+    assert_sorted_null_separated_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' -l rust --files-with-matches -0 src/lib.rs src/stop.rs
+            src/lib.rs\0
+            src/stop.rs\0
+        "#,
+    );
+}
+
+#[test]
+fn test_print0_matches() {
+    assert_sorted_null_separated_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' -l rust --print0-matches
+            pub fn helper() {}\0
+            pub fn add(left: usize, right: usize) -> usize {
+                left + right
+            }\0
+            fn it_works() {
+                    let result = add(2, 2);
+                    assert_eq!(result, 4);
+                }\0
+            fn stop_it() {}\0
+        "#,
+    );
+}
+
+#[test]
+fn test_format_sarif() {
+    assert_json_document_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --format sarif src/stop.rs
+            {
+              "version": "2.1.0",
+              "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+              "runs": [
+                {
+                  "tool": {
+                    "driver": {
+                      "name": "tree-sitter-grep",
+                      "version": "0.1.0",
+                      "informationUri": "https://github.com/helixbass/tree-sitter-grep",
+                      "rules": [{ "id": "name" }]
+                    }
+                  },
+                  "columnKind": "unicodeCodePoints",
+                  "results": [
+                    {
+                      "ruleId": "name",
+                      "message": { "text": "Matched identifier node" },
+                      "locations": [
+                        {
+                          "physicalLocation": {
+                            "artifactLocation": { "uri": "src/stop.rs" },
+                            "region": {
+                              "startLine": 1,
+                              "startColumn": 4,
+                              "endLine": 1,
+                              "endColumn": 11,
+                              "byteOffset": 3,
+                              "byteLength": 7
+                            }
+                          }
+                        }
+                      ]
+                    }
+                  ]
+                }
+              ]
+            }
+        "#,
+    );
+}
+
+#[test]
+fn test_format_sarif_query_file_rule_id_and_message() {
+    assert_json_document_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -Q ./function-item.scm -l rust --format sarif --sarif-message 'Function found' src/stop.rs
+            {
+              "version": "2.1.0",
+              "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+              "runs": [
+                {
+                  "tool": {
+                    "driver": {
+                      "name": "tree-sitter-grep",
+                      "version": "0.1.0",
+                      "informationUri": "https://github.com/helixbass/tree-sitter-grep",
+                      "rules": [{ "id": "function-item" }]
+                    }
+                  },
+                  "columnKind": "unicodeCodePoints",
+                  "results": [
+                    {
+                      "ruleId": "function-item",
+                      "message": { "text": "Function found" },
+                      "locations": [
+                        {
+                          "physicalLocation": {
+                            "artifactLocation": { "uri": "src/stop.rs" },
+                            "region": {
+                              "startLine": 1,
+                              "startColumn": 1,
+                              "endLine": 1,
+                              "endColumn": 16,
+                              "byteOffset": 0,
+                              "byteLength": 15
+                            }
+                          }
+                        }
+                      ]
+                    }
+                  ]
+                }
+              ]
+            }
+        "#,
+    );
+}
+
+#[test]
+fn test_format_sarif_percent_encodes_uri() {
+    assert_json_document_output(
+        "rust_path_with_space",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --format sarif 'src/stop it.rs'
+            {
+              "version": "2.1.0",
+              "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+              "runs": [
+                {
+                  "tool": {
+                    "driver": {
+                      "name": "tree-sitter-grep",
+                      "version": "0.1.0",
+                      "informationUri": "https://github.com/helixbass/tree-sitter-grep",
+                      "rules": [{ "id": "name" }]
+                    }
+                  },
+                  "columnKind": "unicodeCodePoints",
+                  "results": [
+                    {
+                      "ruleId": "name",
+                      "message": { "text": "Matched identifier node" },
+                      "locations": [
+                        {
+                          "physicalLocation": {
+                            "artifactLocation": { "uri": "src/stop%20it.rs" },
+                            "region": {
+                              "startLine": 1,
+                              "startColumn": 4,
+                              "endLine": 1,
+                              "endColumn": 11,
+                              "byteOffset": 3,
+                              "byteLength": 7
+                            }
+                          }
+                        }
+                      ]
+                    }
+                  ]
+                }
+              ]
+            }
+        "#,
+    );
+}
+
+#[test]
+fn test_format_template() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name) @function_item' -l rust --capture function_item --format-template '{path}:{start_line}:{@name}:{kind}'
+            src/helpers.rs:1:helper:function_item
+            src/lib.rs:3:add:function_item
+            src/lib.rs:12:it_works:function_item
+            src/stop.rs:1:stop_it:function_item
+        "#,
+    );
+}
+
+#[test]
+fn test_format_template_positions_and_escaped_braces() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name) @function_item' -l rust --capture function_item --format-template '{{{@name}}} {start_line}:{start_column}-{end_line}:{end_column} [{start_byte}, {end_byte})' src/lib.rs
+            {add} 3:1-5:2 [14, 81)
+            {it_works} 12:5-15:6 [143, 227)
+        "#,
+    );
+}
+
+#[test]
+fn test_format_template_invalid_placeholder() {
+    assert_failure_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' -l rust --format-template '{path}:{line}'
+            error: invalid value '{path}:{line}' for '--format-template <TEMPLATE>': unrecognized placeholder '{line}'. Choose from: path, language, kind, capture, text, start_line, end_line, start_column, end_column, start_byte, end_byte, @{capture name}.
+
+            For more information, try '--help'.
+        "#,
+    );
+}
+
+#[test]
+fn test_format_template_invalid_capture_name() {
+    assert_failure_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' -l rust --format-template '{@namez}'
+            error: invalid capture name 'namez'
+        "#,
+    );
+}
+
+#[test]
+fn test_replace() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust -r '${name}_renamed'
+            src/helpers.rs:1:pub fn helper_renamed() {}
+            src/lib.rs:3:pub fn add_renamed(left: usize, right: usize) -> usize {
+            src/lib.rs:12:    fn it_works_renamed() {
+            src/stop.rs:1:fn stop_it_renamed() {}
+        "#,
+    );
+}
+
+#[test]
+fn test_replace_other_captures() {
+    assert_sorted_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(call_expression function: (identifier) @function arguments: (arguments) @arguments) @call' -l rust --capture call --replace '$function!$arguments /* $$ */'
+            src/lib.rs:13:        let result = add!(2, 2) /* $ */;
+        "#,
+    );
+}
+
+#[test]
+fn test_replace_invalid_capture_name() {
+    assert_failure_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item) @function_item' -l rust -r '$namez'
+            error: invalid capture name 'namez'
+        "#,
+    );
+}
+
+#[test]
+fn test_rewrite_diff() {
+    assert_non_match_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --rewrite '${name}_renamed' --diff src/lib.rs
+            --- a/src/lib.rs
+            +++ b/src/lib.rs
+            @@ -1,6 +1,6 @@
+             mod helpers;
+
+            -pub fn add(left: usize, right: usize) -> usize {
+            +pub fn add_renamed(left: usize, right: usize) -> usize {
+                 left + right
+             }
+
+            @@ -9,7 +9,7 @@
+                 use super::*;
+
+                 #[test]
+            -    fn it_works() {
+            +    fn it_works_renamed() {
+                     let result = add(2, 2);
+                     assert_eq!(result, 4);
+                 }
+        "#,
+    );
+}
+
+#[test]
+fn test_rewrite_write() {
+    assert_rewritten_file(
+        "rust_project",
+        "src/lib.rs",
+        r#"
+            $ tree-sitter-grep -q '(call_expression function: (identifier) @function arguments: (arguments) @arguments) @call' -l rust --capture call --rewrite '$function!$arguments' --write src/lib.rs
+            mod helpers;
+
+            pub fn add(left: usize, right: usize) -> usize {
+                left + right
+            }
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+
+                #[test]
+                fn it_works() {
+                    let result = add!(2, 2);
+                    assert_eq!(result, 4);
+                }
+            }
+
+            mod stop;
+        "#,
+    );
+}
+
+#[test]
+fn test_rewrite_refuses_transcoded_file() {
+    assert_rewrite_failure_output(
+        "rust_bom",
+        "src/utf_16.rs",
+        r#"
+            $ tree-sitter-grep -q '(call_expression function: (identifier) @function) @call' -l rust --capture function --rewrite 'bar' --write src/utf_16.rs
+            File "src/utf_16.rs" was not rewritten because it isn't encoded as UTF-8
+        "#,
+    );
+}
+
+#[test]
+fn test_rewrite_keeps_utf_8_bom() {
+    assert_rewritten_file(
+        "rust_bom",
+        "src/utf_8.rs",
+        "
+            $ tree-sitter-grep -q '(call_expression function: (identifier) @function) @call' -l rust --capture function --rewrite 'bar' --write src/utf_8.rs
+            \u{feff}fn main() {
+                bar(1);
+            }
+        ",
+    );
+}
+
+#[test]
+fn test_rewrite_overlapping_matches() {
+    assert_failure_output(
+        "rust_overlapping",
+        r#"
+            $ tree-sitter-grep -q '(closure_expression) @closure_expression' --language rust --rewrite 'f' --diff
+            File "src/lib.rs" was not rewritten because the matches at 2:13 and 3:9 overlap
+        "#,
+    );
+}
+
+#[test]
+fn test_interactive() {
+    assert_interactive_rewrite(
+        "rust_interactive",
+        "n\ne\nsecond_edited\na\n",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --interactive '${name}_renamed' src/lib.rs
+            --- a/src/lib.rs
+            +++ b/src/lib.rs
+            @@ -1,4 +1,4 @@
+            -fn first() {}
+            +fn first_renamed() {}
+
+             fn second() {}
+
+            (1/4) Rewrite this match [y,n,e,a,q,?]? @@ -1,6 +1,6 @@
+             fn first() {}
+
+            -fn second() {}
+            +fn second_renamed() {}
+
+             fn third() {}
+
+            (2/4) Rewrite this match [y,n,e,a,q,?]? Replacement: @@ -2,6 +2,6 @@
+
+             fn second() {}
+
+            -fn third() {}
+            +fn third_renamed() {}
+
+             fn fourth() {}
+            (3/4) Rewrite this match [y,n,e,a,q,?]?
+        "#,
+        "src/lib.rs",
+        "fn first() {}\n\nfn second_edited() {}\n\nfn third_renamed() {}\n\nfn fourth_renamed() {}\n",
+    );
+}
+
+#[test]
+fn test_interactive_quit() {
+    assert_interactive_rewrite(
+        "rust_interactive",
+        "y\nx\nq\n",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --interactive '${name}_renamed' src/lib.rs
+            --- a/src/lib.rs
+            +++ b/src/lib.rs
+            @@ -1,4 +1,4 @@
+            -fn first() {}
+            +fn first_renamed() {}
+
+             fn second() {}
+
+            (1/4) Rewrite this match [y,n,e,a,q,?]? @@ -1,6 +1,6 @@
+             fn first() {}
+
+            -fn second() {}
+            +fn second_renamed() {}
+
+             fn third() {}
+
+            (2/4) Rewrite this match [y,n,e,a,q,?]? y - rewrite this match
+            n - don't rewrite this match
+            e - manually edit the replacement for this match
+            a - rewrite this match and all the remaining ones in this file
+            q - quit; don't rewrite this match or any of the remaining ones
+            (2/4) Rewrite this match [y,n,e,a,q,?]?
+        "#,
+        "src/lib.rs",
+        "fn first_renamed() {}\n\nfn second() {}\n\nfn third() {}\n\nfn fourth() {}\n",
+    );
+}
+
+#[test]
+fn test_interactive_rewrite_of_file_modified_during_review() {
+    assert_interactive_rewrite_of_modified_file(
+        "rust_interactive",
+        "src/lib.rs",
+        "fn first() {}\n",
+        "a\n",
+        r#"
+            $ tree-sitter-grep -q '(function_item name: (identifier) @name)' -l rust --interactive '${name}_renamed' src/lib.rs
+            File "src/lib.rs" was not rewritten because it was modified during review
+        "#,
+    );
+}
+
+#[test]
+fn test_new_parse_errors_since() {
+    assert_sorted_failure_output_in_git_repo(
+        "rust_parse_errors_committed",
+        "rust_parse_errors_working_tree",
+        r#"
+            $ tree-sitter-grep --new-parse-errors-since HEAD
+            src/added.rs:2:10: ERROR
+            src/lib.rs:11:14: MISSING ";"
+            src/lib.rs:12:15: MISSING ")"
+            src/lib.rs:13:11: ERROR
+        "#,
+        r#"New parse errors were found since revision "HEAD""#,
+    );
+}
+
+#[test]
+fn test_new_parse_errors_since_invalid_revision() {
+    assert_failure_output(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep --new-parse-errors-since no-such-revision
+            error: couldn't resolve git revision "no-such-revision"
+        "#,
+    );
+}
+
+#[test]
+fn test_new_parse_errors_since_outside_git_repo() {
+    assert_failure_output_outside_git_repo(
+        "rust_project",
+        r#"
+            $ tree-sitter-grep --new-parse-errors-since HEAD
+            error: git failed: not a git repository (or any of the parent directories): .git
+        "#,
+    );
+}
@@ -1,9 +1,17 @@
 #![allow(clippy::into_iter_on_ref, clippy::collapsible_if, dead_code)]
-use std::{borrow::Cow, env, path::PathBuf, process::Command};
+use std::{
+    borrow::Cow,
+    env, fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use assert_cmd::prelude::*;
 use predicates::prelude::*;
 use regex::Captures;
+use serde_json::Value;
 
 #[macro_export]
 macro_rules! regex {
@@ -22,6 +30,64 @@ fn get_fixture_dir_path_from_name(fixture_dir_name: &str) -> PathBuf {
     path
 }
 
+fn copy_dir_recursively(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let to = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_recursively(&entry.path(), &to)?;
+        } else {
+            fs::copy(entry.path(), to)?;
+        }
+    }
+    Ok(())
+}
+
+// so that tests which modify files don't touch the checked-in fixtures
+fn copy_fixture_dir_to_temp_dir(fixture_dir_name: &str) -> PathBuf {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+    let mut path: PathBuf = env!("CARGO_TARGET_TMPDIR").into();
+    path.push(format!(
+        "{fixture_dir_name}-{}-{}",
+        std::process::id(),
+        NEXT_ID.fetch_add(1, Ordering::SeqCst)
+    ));
+    if path.exists() {
+        fs::remove_dir_all(&path).unwrap();
+    }
+    copy_dir_recursively(&get_fixture_dir_path_from_name(fixture_dir_name), &path).unwrap();
+    path
+}
+
+fn run_git(repo_path: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(repo_path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+// commits the first fixture and then overwrites the working tree with the
+// second one
+fn copy_fixture_dirs_to_temp_git_repo(
+    committed_fixture_dir_name: &str,
+    working_tree_fixture_dir_name: &str,
+) -> PathBuf {
+    let path = copy_fixture_dir_to_temp_dir(committed_fixture_dir_name);
+    run_git(&path, &["init", "--quiet"]);
+    run_git(&path, &["add", "."]);
+    run_git(&path, &["commit", "--quiet", "-m", "Committed fixture"]);
+    copy_dir_recursively(
+        &get_fixture_dir_path_from_name(working_tree_fixture_dir_name),
+        &path,
+    )
+    .unwrap();
+    path
+}
+
 fn parse_command_and_output(command_and_output: &str) -> CommandAndOutput {
     let mut lines = command_and_output.split('\n').collect::<Vec<_>>();
     if lines.is_empty() {
@@ -145,7 +211,7 @@ fn strip_trailing_carriage_return(line: &str) -> Cow<'_, str> {
 }
 
 fn normalize_match_path(line: &str) -> Cow<'_, str> {
-    regex!(r#"^[^:]+[:-]\d+[:-]"#)
+    regex!(r#"^[^:]+[:-]\d+(?:[:-]|$)"#)
         .replace(line, |captures: &Captures| captures[0].replace('\\', "/"))
 }
 
@@ -160,6 +226,135 @@ fn do_sorted_lines_match(actual_output: &str, expected_output: &str) -> bool {
     actual_lines == expected_lines
 }
 
+fn without_elapsed_fields(mut value: Value) -> Value {
+    if let Value::Object(map) = &mut value {
+        map.retain(|key, _| !key.starts_with("elapsed"));
+        for nested in map.values_mut() {
+            *nested = without_elapsed_fields(nested.take());
+        }
+    }
+    value
+}
+
+fn normalize_json_lines(output: &str) -> Vec<Value> {
+    let mut values = output
+        .split('\n')
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| without_elapsed_fields(serde_json::from_str(line).unwrap()))
+        .collect::<Vec<_>>();
+    values.sort_by_key(|value| value.to_string());
+    values
+}
+
+pub fn assert_sorted_output_and_error_output(
+    fixture_dir_name: &str,
+    command_and_output: &str,
+    error_output: &str,
+) {
+    let CommandAndOutput {
+        mut command_line_args,
+        output,
+    } = parse_command_and_output(command_and_output);
+    let command_name = command_line_args.remove(0);
+    Command::cargo_bin(command_name)
+        .unwrap()
+        .args(command_line_args)
+        .current_dir(get_fixture_dir_path_from_name(fixture_dir_name))
+        .assert()
+        .success()
+        .stdout(predicate::function(|stdout: &str| {
+            do_sorted_lines_match(stdout, &output)
+        }))
+        .stderr(predicate::function(|stderr: &str| {
+            do_sorted_lines_match(&massage_error_output(stderr), error_output)
+        }));
+}
+
+pub fn assert_sorted_json_output(fixture_dir_name: &str, command_and_output: &str) {
+    let CommandAndOutput {
+        mut command_line_args,
+        output,
+    } = parse_command_and_output(command_and_output);
+    let command_name = command_line_args.remove(0);
+    Command::cargo_bin(command_name)
+        .unwrap()
+        .args(command_line_args)
+        .current_dir(get_fixture_dir_path_from_name(fixture_dir_name))
+        .assert()
+        .success()
+        .stdout(predicate::function(|stdout: &str| {
+            normalize_json_lines(stdout) == normalize_json_lines(&output)
+        }));
+}
+
+pub fn assert_json_document_output(fixture_dir_name: &str, command_and_output: &str) {
+    let CommandAndOutput {
+        mut command_line_args,
+        output,
+    } = parse_command_and_output(command_and_output);
+    let command_name = command_line_args.remove(0);
+    let expected: Value = serde_json::from_str(&output).unwrap();
+    Command::cargo_bin(command_name)
+        .unwrap()
+        .args(command_line_args)
+        .current_dir(get_fixture_dir_path_from_name(fixture_dir_name))
+        .assert()
+        .success()
+        .stdout(predicate::function(|stdout: &str| {
+            serde_json::from_str::<Value>(stdout).unwrap() == expected
+        }));
+}
+
+fn without_durations(output: &str) -> String {
+    regex!(r#"\d+\.\d{6} seconds"#)
+        .replace_all(output, "<duration> seconds")
+        .into_owned()
+}
+
+pub fn assert_sorted_stats_output(fixture_dir_name: &str, command_and_output: &str) {
+    let CommandAndOutput {
+        mut command_line_args,
+        output,
+    } = parse_command_and_output(command_and_output);
+    let command_name = command_line_args.remove(0);
+    Command::cargo_bin(command_name)
+        .unwrap()
+        .args(command_line_args)
+        .current_dir(get_fixture_dir_path_from_name(fixture_dir_name))
+        .assert()
+        .success()
+        .stdout(predicate::function(|stdout: &str| {
+            do_sorted_lines_match(&without_durations(stdout), &output)
+        }));
+}
+
+fn do_sorted_null_separated_items_match(actual_output: &str, expected_output: &str) -> bool {
+    let mut actual_items = actual_output.split('\0').collect::<Vec<_>>();
+    actual_items.sort();
+    let expected_output = expected_output.replace("\\0\n", "\0");
+    let mut expected_items = expected_output.split('\0').collect::<Vec<_>>();
+    expected_items.sort();
+    actual_items == expected_items
+}
+
+// expected output marks each NUL byte with a literal "\0" at the end of a line
+pub fn assert_sorted_null_separated_output(fixture_dir_name: &str, command_and_output: &str) {
+    let CommandAndOutput {
+        mut command_line_args,
+        output,
+    } = parse_command_and_output(command_and_output);
+    let command_name = command_line_args.remove(0);
+    Command::cargo_bin(command_name)
+        .unwrap()
+        .args(command_line_args)
+        .current_dir(get_fixture_dir_path_from_name(fixture_dir_name))
+        .assert()
+        .success()
+        .stdout(predicate::function(|stdout: &str| {
+            do_sorted_null_separated_items_match(stdout, &output)
+        }));
+}
+
 pub fn assert_failure_output(fixture_dir_name: &str, command_and_output: &str) {
     let CommandAndOutput {
         mut command_line_args,
@@ -197,6 +392,216 @@ pub fn assert_non_match_output(fixture_dir_name: &str, command_and_output: &str)
         }));
 }
 
+pub fn assert_stdin_output(fixture_dir_name: &str, input: &str, command_and_output: &str) {
+    let CommandAndOutput {
+        mut command_line_args,
+        output,
+    } = parse_command_and_output(command_and_output);
+    let command_name = command_line_args.remove(0);
+    Command::cargo_bin(command_name)
+        .unwrap()
+        .args(command_line_args)
+        .current_dir(get_fixture_dir_path_from_name(fixture_dir_name))
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::function(|stdout: &str| {
+            let stdout = massage_error_output(stdout);
+            stdout == output
+        }));
+}
+
+pub fn assert_rewritten_file(
+    fixture_dir_name: &str,
+    rewritten_file_path: &str,
+    command_and_output: &str,
+) {
+    let CommandAndOutput {
+        mut command_line_args,
+        output,
+    } = parse_command_and_output(command_and_output);
+    let temp_dir_path = copy_fixture_dir_to_temp_dir(fixture_dir_name);
+    let command_name = command_line_args.remove(0);
+    Command::cargo_bin(command_name)
+        .unwrap()
+        .args(command_line_args)
+        .current_dir(&temp_dir_path)
+        .assert()
+        .success()
+        .stdout("");
+    let rewritten = fs::read_to_string(temp_dir_path.join(rewritten_file_path)).unwrap();
+    assert_eq!(rewritten, output);
+    fs::remove_dir_all(temp_dir_path).unwrap();
+}
+
+pub fn assert_rewrite_failure_output(
+    fixture_dir_name: &str,
+    unchanged_file_path: &str,
+    command_and_output: &str,
+) {
+    let CommandAndOutput {
+        mut command_line_args,
+        output,
+    } = parse_command_and_output(command_and_output);
+    let temp_dir_path = copy_fixture_dir_to_temp_dir(fixture_dir_name);
+    let original = fs::read(temp_dir_path.join(unchanged_file_path)).unwrap();
+    let command_name = command_line_args.remove(0);
+    Command::cargo_bin(command_name)
+        .unwrap()
+        .args(command_line_args)
+        .current_dir(&temp_dir_path)
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::function(|stderr: &str| {
+            let stderr = massage_error_output(stderr);
+            stderr == output
+        }));
+    let contents = fs::read(temp_dir_path.join(unchanged_file_path)).unwrap();
+    assert_eq!(contents, original);
+    fs::remove_dir_all(temp_dir_path).unwrap();
+}
+
+// the prompts don't end with a newline, so only whole lines are compared
+pub fn assert_interactive_rewrite(
+    fixture_dir_name: &str,
+    input: &str,
+    command_and_output: &str,
+    rewritten_file_path: &str,
+    rewritten_file_contents: &str,
+) {
+    let CommandAndOutput {
+        mut command_line_args,
+        output,
+    } = parse_command_and_output(command_and_output);
+    let temp_dir_path = copy_fixture_dir_to_temp_dir(fixture_dir_name);
+    let command_name = command_line_args.remove(0);
+    Command::cargo_bin(command_name)
+        .unwrap()
+        .args(command_line_args)
+        .current_dir(&temp_dir_path)
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(predicate::function(|stdout: &str| {
+            massage_error_output(stdout)
+                .lines()
+                .eq(massage_error_output(&output).lines())
+        }));
+    let rewritten = fs::read_to_string(temp_dir_path.join(rewritten_file_path)).unwrap();
+    assert_eq!(rewritten, rewritten_file_contents);
+    fs::remove_dir_all(temp_dir_path).unwrap();
+}
+
+// `modified_file_contents` get written over `modified_file_path` once the
+// first prompt has been shown, before `input` gets answered
+pub fn assert_interactive_rewrite_of_modified_file(
+    fixture_dir_name: &str,
+    modified_file_path: &str,
+    modified_file_contents: &str,
+    input: &str,
+    command_and_error_output: &str,
+) {
+    let CommandAndOutput {
+        mut command_line_args,
+        output,
+    } = parse_command_and_output(command_and_error_output);
+    let temp_dir_path = copy_fixture_dir_to_temp_dir(fixture_dir_name);
+    let command_name = command_line_args.remove(0);
+    let mut child = Command::cargo_bin(command_name)
+        .unwrap()
+        .args(command_line_args)
+        .current_dir(&temp_dir_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    let mut prompted = vec![];
+    let mut buffer = [0; 1024];
+    while !String::from_utf8_lossy(&prompted).contains("Rewrite this match") {
+        let read = stdout.read(&mut buffer).unwrap();
+        assert!(read > 0, "Expected a prompt");
+        prompted.extend_from_slice(&buffer[..read]);
+    }
+    fs::write(
+        temp_dir_path.join(modified_file_path),
+        modified_file_contents,
+    )
+    .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    io::copy(&mut stdout, &mut io::sink()).unwrap();
+    let result = child.wait_with_output().unwrap();
+    assert_eq!(result.status.code(), Some(2));
+    assert_eq!(
+        massage_error_output(&String::from_utf8(result.stderr).unwrap()),
+        output
+    );
+    let contents = fs::read_to_string(temp_dir_path.join(modified_file_path)).unwrap();
+    assert_eq!(contents, modified_file_contents);
+    fs::remove_dir_all(temp_dir_path).unwrap();
+}
+
+pub fn assert_failure_output_outside_git_repo(fixture_dir_name: &str, command_and_output: &str) {
+    let CommandAndOutput {
+        mut command_line_args,
+        output,
+    } = parse_command_and_output(command_and_output);
+    let temp_dir_path = copy_fixture_dir_to_temp_dir(fixture_dir_name);
+    let command_name = command_line_args.remove(0);
+    Command::cargo_bin(command_name)
+        .unwrap()
+        .args(command_line_args)
+        .current_dir(&temp_dir_path)
+        // keeps git from finding this repository
+        .env("GIT_CEILING_DIRECTORIES", temp_dir_path.parent().unwrap())
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::function(|stderr: &str| {
+            let stderr = massage_error_output(stderr);
+            stderr == output
+        }));
+    fs::remove_dir_all(temp_dir_path).unwrap();
+}
+
+pub fn assert_sorted_failure_output_in_git_repo(
+    committed_fixture_dir_name: &str,
+    working_tree_fixture_dir_name: &str,
+    command_and_output: &str,
+    error_output: &str,
+) {
+    let CommandAndOutput {
+        mut command_line_args,
+        output,
+    } = parse_command_and_output(command_and_output);
+    let temp_dir_path = copy_fixture_dirs_to_temp_git_repo(
+        committed_fixture_dir_name,
+        working_tree_fixture_dir_name,
+    );
+    let command_name = command_line_args.remove(0);
+    Command::cargo_bin(command_name)
+        .unwrap()
+        .args(command_line_args)
+        .current_dir(&temp_dir_path)
+        .assert()
+        .failure()
+        .code(2)
+        .stdout(predicate::function(|stdout: &str| {
+            do_sorted_lines_match(stdout, &output)
+        }))
+        .stderr(predicate::function(|stderr: &str| {
+            massage_error_output(stderr).trim_end() == error_output
+        }));
+    fs::remove_dir_all(temp_dir_path).unwrap();
+}
+
 fn massage_error_output(output: &str) -> String {
     if cfg!(windows) {
         output.replace(".exe", "").replace(